@@ -1,48 +1,1881 @@
 #![cfg_attr(not(feature = "export-abi"), no_main)]
 extern crate alloc;
 
-use alloy_primitives::{Address, U256};
+mod signatures;
+
+use alloc::vec::Vec;
+use alloy_primitives::{b256, Address, B256, U256, U64};
 use alloy_sol_types::sol;
-use stylus_sdk::{msg, prelude::*};
+use stylus_common::access_control::{
+    AccessControl, AccessControlBadConfirmation, AccessControlUnauthorizedAccount,
+    DEFAULT_ADMIN_ROLE,
+};
+use stylus_common::erc20::IERC20;
+use stylus_common::ownable::{
+    InvalidInitialization, Ownable, OwnableInvalidOwner, OwnableUnauthorizedAccount,
+};
+use stylus_common::pausable::{EnforcedPause, ExpectedPause, Pausable};
+use stylus_sdk::{
+    abi::Bytes,
+    block,
+    call::{self, Call},
+    contract, crypto, evm, msg,
+    prelude::*,
+};
+
+use crate::signatures::{SessionReceipt, UsageReceipt};
 
 sol_storage! {
     #[entrypoint]
     pub struct ApiAuthorization {
-        mapping(address => uint256) accessings;
+        mapping(address => mapping(uint256 => uint256)) accessings;
+
+        #[borrow]
+        Ownable ownable;
+
+        uint256[] service_ids;
+        mapping(uint256 => Service) services;
+
+        uint256 plan_count;
+        mapping(uint256 => Plan) plans;
+        mapping(address => mapping(uint256 => Subscription)) subscriptions;
+
+        mapping(address => mapping(address => mapping(uint256 => uint256))) allowances;
+
+        mapping(uint256 => uint256) supplies;
+        bool token_service_set;
+        uint256 token_service;
+
+        mapping(address => uint256) usage_nonces;
+        mapping(address => mapping(uint256 => uint256)) settled_usage;
+
+        uint256 session_count;
+        mapping(uint256 => Session) sessions;
+
+        #[borrow]
+        Pausable pausable;
+        #[borrow]
+        AccessControl access_control;
+
+        mapping(address => UsageStats) usage_stats;
+
+        mapping(bytes32 => PromoCode) promo_codes;
+
+        RateLimit default_rate_limit;
+        mapping(address => RateLimit) rate_limits;
+        mapping(address => RateUsage) rate_usage;
+
+        mapping(address => mapping(uint256 => CreditLots)) credit_lots;
+        uint64 credit_lifetime;
+    }
+
+    pub struct Service {
+        bool registered;
+        uint256 price;
+        address[] accepted_tokens;
+        mapping(address => uint256) token_prices;
+        PriceTier[] price_tiers;
+    }
+
+    pub struct PriceTier {
+        uint256 min_value;
+        uint256 price;
+    }
+
+    pub struct PromoCode {
+        uint256 service_id;
+        uint256 bonus;
+        uint256 max_uses;
+        uint256 uses;
+        uint64 expires_at;
+        mapping(address => bool) redeemed;
+    }
+
+    pub struct Plan {
+        bool active;
+        uint256 service_id;
+        uint256 price;
+        uint64 duration;
+        uint256 call_cap;
+    }
+
+    pub struct Subscription {
+        uint256 plan_id;
+        uint64 expires_at;
+        uint256 call_cap;
+        uint256 calls;
+    }
+
+    pub struct UsageStats {
+        uint256 purchased;
+        uint256 consumed;
+        mapping(uint256 => uint256) epoch_usage;
+    }
+
+    pub struct CreditLots {
+        uint256 head;
+        uint256 total;
+        CreditLot[] lots;
+    }
+
+    pub struct CreditLot {
+        uint256 amount;
+        uint64 expires_at;
+    }
+
+    pub struct RateLimit {
+        bool custom;
+        uint64 block_window;
+        uint256 per_window;
+        uint256 per_hour;
+    }
+
+    pub struct RateUsage {
+        uint256 window;
+        uint256 window_calls;
+        uint256 hour;
+        uint256 hour_calls;
+    }
+
+    pub struct Session {
+        address user;
+        address operator;
+        uint256 service_id;
+        uint256 deposit;
+        uint64 expires_at;
+        bool closed;
+        CreditLot[] lots;
     }
 }
 
+/// keccak256("OPERATOR_ROLE"); held by API backend wallets that meter usage on
+/// behalf of users.
+const OPERATOR_ROLE: B256 =
+    b256!("97667070c54ef182b0f5858b034beac1b6f3089aa2d3188bb1e8929f4fa9b929");
+
+/// Length of the periods usage is bucketed into, counted from the Unix epoch.
+const EPOCH_LENGTH: u64 = 86_400;
+
+/// keccak256("GRANTER_ROLE"); may hand out free, expiring credits.
+const GRANTER_ROLE: B256 =
+    b256!("2dee5dd865e09c7ce6788d674a03682994f0b44339403ca07ac129a9de4bed6a");
+
+const HOUR: u64 = 3_600;
+
+/// Minted credits lapse at the end of a whole week, so purchases made days
+/// apart share a lot.
+const EXPIRY_GRANULARITY: u64 = 7 * 86_400;
+/// Most live lots a holder keeps per service, bounding the work of spending
+/// and of receiving credits with yet another expiry.
+const MAX_CREDIT_LOTS: usize = 64;
+/// How long purchased accessings stay usable until the admin changes it.
+const DEFAULT_CREDIT_LIFETIME: u64 = 365 * 86_400;
+
+const TOKEN_NAME: &str = "API Access Credits";
+const TOKEN_SYMBOL: &str = "APIC";
+
 sol! {
-    event Purchase(address addr, uint256 accessings);
+    event Transfer(address indexed from, address indexed to, uint256 value);
+    event Approval(address indexed owner, address indexed spender, uint256 value);
+    event TokenServiceSet(uint256 indexed service_id);
+    event UsageSettled(address indexed user, uint256 indexed service_id, uint256 used, uint256 charged);
+    event UsageNonceIncreased(address indexed user, uint256 nonce);
+    event SessionOpened(uint256 indexed session_id, address indexed user, address indexed operator, uint256 service_id, uint256 deposit, uint64 expires_at);
+    event SessionClosed(uint256 indexed session_id, uint256 used, uint256 refunded);
+    event SessionReclaimed(uint256 indexed session_id, uint256 refunded);
+    event Purchase(address addr, uint256 service_id, uint256 accessings);
+    event ServiceRegistered(uint256 indexed service_id, uint256 price);
+    event TokenPriceSet(uint256 indexed service_id, address indexed token, uint256 price);
+    event PriceTiersSet(uint256 indexed service_id, uint256[] min_values, uint256[] prices);
+    event PromoCodeCreated(bytes32 indexed code_hash, uint256 indexed service_id, uint256 bonus, uint256 max_uses, uint64 expires_at);
+    event PromoCodeRedeemed(bytes32 indexed code_hash, address indexed user, uint256 bonus);
+    event RateLimitSet(address indexed user, uint64 block_window, uint256 per_window, uint256 per_hour);
+    event PlanCreated(uint256 indexed plan_id, uint256 indexed service_id, uint256 price, uint64 duration, uint256 call_cap);
+    event PlanActiveSet(uint256 indexed plan_id, bool active);
+    event Subscribed(address indexed user, uint256 indexed plan_id, uint64 expires_at);
+    event CreditsTransferred(address indexed from, address indexed to, uint256 indexed service_id, uint256 amount);
+    event CreditsGranted(address indexed user, uint256 indexed service_id, uint256 amount, uint64 expires_at);
+    event CreditsExpired(address indexed user, uint256 indexed service_id, uint256 amount);
+    event CreditLifetimeSet(uint64 lifetime);
+    event CreditsApproval(address indexed owner, address indexed spender, uint256 indexed service_id, uint256 amount);
+    event Withdrawn(address indexed to, uint256 amount);
+    event TokenWithdrawn(address indexed token, address indexed to, uint256 amount);
+
+    error NotAuthorized(address caller);
+    error UnknownService(uint256 service_id);
+    error InvalidPrice();
+    error InsufficientCredits(address user, uint256 service_id);
+    error RateLimited(address user);
+    error InvalidPriceTiers();
+    error InvalidPromoCode(bytes32 code_hash);
+    error PromoCodeAlreadyRedeemed(bytes32 code_hash, address user);
+    error TokenNotAccepted(address token);
+    error InsufficientPayment(uint256 amount, uint256 price);
+    error IncorrectPayment(uint256 paid, uint256 price);
+    error TokenTransferFailed(address token);
+    error WithdrawalFailed(address to);
+    error UnknownPlan(uint256 plan_id);
+    error SubscriptionActive(address user, uint256 service_id);
+    error InsufficientAllowance(address owner, address spender, uint256 service_id);
+    error InvalidRecipient(address to);
+    error TokenServiceAlreadySet(uint256 service_id);
+    error TokenServiceNotSet();
+    error TokenServiceHasSupply(uint256 service_id, uint256 supply);
+    error InvalidSignature();
+    error InvalidNonce(uint256 nonce);
+    error StaleReceipt(address user, uint256 service_id, uint256 used);
+    error InvalidExpiry(uint64 expires_at);
+    error TooManyCreditLots(address holder, uint256 service_id);
+    error UnknownSession(uint256 session_id);
+    error SessionExpired(uint256 session_id);
+    error SessionNotExpired(uint256 session_id);
+    error SessionOverdrawn(uint256 session_id, uint256 used, uint256 deposit);
+    error EscrowNotSpendable();
+}
+
+#[derive(SolidityError)]
+pub enum ApiAuthorizationError {
+    NotAuthorized(NotAuthorized),
+    UnknownService(UnknownService),
+    InvalidPrice(InvalidPrice),
+    RateLimited(RateLimited),
+    InvalidPriceTiers(InvalidPriceTiers),
+    InvalidPromoCode(InvalidPromoCode),
+    PromoCodeAlreadyRedeemed(PromoCodeAlreadyRedeemed),
+    InsufficientCredits(InsufficientCredits),
+    TokenNotAccepted(TokenNotAccepted),
+    InsufficientPayment(InsufficientPayment),
+    IncorrectPayment(IncorrectPayment),
+    TokenTransferFailed(TokenTransferFailed),
+    WithdrawalFailed(WithdrawalFailed),
+    UnknownPlan(UnknownPlan),
+    SubscriptionActive(SubscriptionActive),
+    InsufficientAllowance(InsufficientAllowance),
+    InvalidRecipient(InvalidRecipient),
+    TokenServiceAlreadySet(TokenServiceAlreadySet),
+    TokenServiceNotSet(TokenServiceNotSet),
+    TokenServiceHasSupply(TokenServiceHasSupply),
+    InvalidSignature(InvalidSignature),
+    InvalidNonce(InvalidNonce),
+    StaleReceipt(StaleReceipt),
+    InvalidExpiry(InvalidExpiry),
+    TooManyCreditLots(TooManyCreditLots),
+    UnknownSession(UnknownSession),
+    SessionExpired(SessionExpired),
+    SessionNotExpired(SessionNotExpired),
+    SessionOverdrawn(SessionOverdrawn),
+    EscrowNotSpendable(EscrowNotSpendable),
+    EnforcedPause(EnforcedPause),
+    ExpectedPause(ExpectedPause),
+    AccessControlUnauthorizedAccount(AccessControlUnauthorizedAccount),
+    AccessControlBadConfirmation(AccessControlBadConfirmation),
+    InvalidInitialization(InvalidInitialization),
+    OwnableUnauthorizedAccount(OwnableUnauthorizedAccount),
+    OwnableInvalidOwner(OwnableInvalidOwner),
+}
+
+stylus_common::impl_component_errors!(ApiAuthorizationError);
+
+/// Index of a rate limit window and the calls already made in it, or `None`
+/// when the limit is disabled.
+type RateWindow = Option<(U256, U256)>;
+
+/// Sort key of a lot expiring at `expires_at`; lots that never lapse last.
+fn lot_order(expires_at: u64) -> u64 {
+    if expires_at == 0 {
+        u64::MAX
+    } else {
+        expires_at
+    }
+}
+
+/// Rounds a minted expiry up to the next multiple of `EXPIRY_GRANULARITY`,
+/// or `None` if that no longer fits.
+fn round_expiry(expires_at: u64) -> Option<u64> {
+    expires_at
+        .div_ceil(EXPIRY_GRANULARITY)
+        .checked_mul(EXPIRY_GRANULARITY)
+}
+
+/// Rounded expiry of accessings minted now that last `lifetime` seconds.
+fn expiry_after(lifetime: u64) -> Result<u64, ApiAuthorizationError> {
+    let now = block::timestamp();
+    now.checked_add(lifetime)
+        .and_then(round_expiry)
+        .ok_or(ApiAuthorizationError::InvalidExpiry(InvalidExpiry {
+            expires_at: now.saturating_add(lifetime),
+        }))
+}
+
+impl RateLimit {
+    fn values(&self) -> (u64, U256, U256) {
+        (
+            self.block_window.get().to::<u64>(),
+            self.per_window.get(),
+            self.per_hour.get(),
+        )
+    }
+
+    fn set(&mut self, block_window: u64, per_window: U256, per_hour: U256) {
+        self.block_window.set(U64::from(block_window));
+        self.per_window.set(per_window);
+        self.per_hour.set(per_hour);
+    }
+}
+
+impl ApiAuthorization {
+    fn only_role(&self, role: B256) -> Result<(), ApiAuthorizationError> {
+        Ok(self.access_control.only_role(role)?)
+    }
+
+    fn registered_service(&self, service_id: U256) -> Result<(), ApiAuthorizationError> {
+        if !self.services.getter(service_id).registered.get() {
+            return Err(ApiAuthorizationError::UnknownService(UnknownService {
+                service_id,
+            }));
+        }
+        Ok(())
+    }
+
+    /// Price per accessing for a purchase worth `value` wei: that of the
+    /// highest tier `value` reaches, or the base price below every tier.
+    fn price_for(&self, service_id: U256, value: U256) -> U256 {
+        let service = self.services.getter(service_id);
+        let mut price = service.price.get();
+        for i in 0..service.price_tiers.len() {
+            let tier = service.price_tiers.get(i).unwrap();
+            if value < tier.min_value.get() {
+                break;
+            }
+            price = tier.price.get();
+        }
+        price
+    }
+
+    /// Accessings of `service_id` the ETH sent along buys at tiered prices,
+    /// failing if it doesn't cover a single one.
+    fn accessings_for_value(&self, service_id: U256) -> Result<U256, ApiAuthorizationError> {
+        self.registered_service(service_id)?;
+        let price = self.price_for(service_id, msg::value());
+        let accessings = msg::value() / price;
+        if accessings.is_zero() {
+            return Err(ApiAuthorizationError::InsufficientPayment(
+                InsufficientPayment {
+                    amount: msg::value(),
+                    price,
+                },
+            ));
+        }
+        Ok(accessings)
+    }
+
+    /// Returns `(block_window, per_window, per_hour)` applying to `user`: their
+    /// own limits if the admin set any, the default ones otherwise.
+    fn rate_limit_of(&self, user: Address) -> (u64, U256, U256) {
+        let custom = self.rate_limits.getter(user);
+        if custom.custom.get() {
+            return custom.values();
+        }
+        self.default_rate_limit.values()
+    }
+
+    /// Current block window and hour of `user`'s limits.
+    fn rate_windows(&self, user: Address) -> (RateWindow, RateWindow) {
+        let (block_window, per_window, per_hour) = self.rate_limit_of(user);
+        let usage = self.rate_usage.getter(user);
+        let calls_in = |current: U256, recorded: U256, calls: U256| {
+            (
+                current,
+                if current == recorded {
+                    calls
+                } else {
+                    U256::ZERO
+                },
+            )
+        };
+        let window = (block_window != 0 && !per_window.is_zero()).then(|| {
+            let current = U256::from(block::number() / block_window);
+            calls_in(current, usage.window.get(), usage.window_calls.get())
+        });
+        let hour = (!per_hour.is_zero()).then(|| {
+            let current = U256::from(block::timestamp() / HOUR);
+            calls_in(current, usage.hour.get(), usage.hour_calls.get())
+        });
+        (window, hour)
+    }
+
+    /// Counts one `mark_usage` call of `user` against their rate limits.
+    fn consume_rate(&mut self, user: Address) -> Result<(), ApiAuthorizationError> {
+        let (_, per_window, per_hour) = self.rate_limit_of(user);
+        let (window, hour) = self.rate_windows(user);
+        if window.is_some_and(|(_, calls)| calls >= per_window)
+            || hour.is_some_and(|(_, calls)| calls >= per_hour)
+        {
+            return Err(ApiAuthorizationError::RateLimited(RateLimited { user }));
+        }
+
+        let mut usage = self.rate_usage.setter(user);
+        if let Some((current, calls)) = window {
+            usage.window.set(current);
+            usage.window_calls.set(calls + U256::from(1));
+        }
+        if let Some((current, calls)) = hour {
+            usage.hour.set(current);
+            usage.hour_calls.set(calls + U256::from(1));
+        }
+        Ok(())
+    }
+
+    /// Whether `user` holds an unexpired subscription to `service_id` that still
+    /// has calls left under its cap.
+    fn subscription_covers(&self, user: Address, service_id: U256) -> bool {
+        let subscriptions = self.subscriptions.getter(user);
+        let subscription = subscriptions.getter(service_id);
+        let call_cap = subscription.call_cap.get();
+        block::timestamp() < subscription.expires_at.get().to::<u64>()
+            && (call_cap.is_zero() || subscription.calls.get() < call_cap)
+    }
+
+    fn is_token_service(&self, service_id: U256) -> bool {
+        self.token_service_set.get() && self.token_service.get() == service_id
+    }
+
+    /// Service behind the ERC-20 entry points, which revert until one is set.
+    fn erc20_service(&self) -> Result<U256, ApiAuthorizationError> {
+        if !self.token_service_set.get() {
+            return Err(ApiAuthorizationError::TokenServiceNotSet(
+                TokenServiceNotSet {},
+            ));
+        }
+        Ok(self.token_service.get())
+    }
+
+    /// Mirrors a balance change of the token service as an ERC-20 `Transfer`,
+    /// with the zero address standing for mints and burns.
+    fn log_token_transfer(&self, from: Address, to: Address, service_id: U256, value: U256) {
+        if self.is_token_service(service_id) {
+            evm::log(Transfer { from, to, value });
+        }
+    }
+
+    /// Takes `amount` live accessings from `holder`, soonest-expiring lots
+    /// first, and returns them as `(amount, expires_at)` chunks. Expired lots
+    /// at the front are burned on the way, whatever `amount` is. Credits held
+    /// from before lots existed never lapse and go last. The contract's own
+    /// balance is session escrow, only ever released by settling a session.
+    fn take_credits(
+        &mut self,
+        holder: Address,
+        service_id: U256,
+        amount: U256,
+    ) -> Result<Vec<(U256, u64)>, ApiAuthorizationError> {
+        if holder == contract::address() {
+            return Err(ApiAuthorizationError::EscrowNotSpendable(
+                EscrowNotSpendable {},
+            ));
+        }
+        let now = block::timestamp();
+        let balance = self.accessings.getter(holder).get(service_id);
+        let mut chunks = Vec::new();
+
+        let mut lots = self.credit_lots.setter(holder);
+        let mut lots = lots.setter(service_id);
+        let len = lots.lots.len();
+        let mut head = lots.head.get().to::<usize>();
+        let mut remaining = amount;
+        let mut expired = U256::ZERO;
+        while head < len {
+            let mut lot = lots.lots.setter(head).unwrap();
+            let lot_amount = lot.amount.get();
+            let expires_at = lot.expires_at.get().to::<u64>();
+            let taken = if expires_at != 0 && expires_at <= now {
+                expired += lot_amount;
+                lot_amount
+            } else if remaining.is_zero() {
+                break;
+            } else {
+                let taken = lot_amount.min(remaining);
+                remaining -= taken;
+                chunks.push((taken, expires_at));
+                taken
+            };
+            lot.amount.set(lot_amount - taken);
+            if taken != lot_amount {
+                break;
+            }
+            head += 1;
+        }
+        let total = lots.total.get() - (amount - remaining) - expired;
+
+        let unlotted = balance - lots.total.get();
+        if remaining > unlotted {
+            return Err(ApiAuthorizationError::InsufficientCredits(
+                InsufficientCredits {
+                    user: holder,
+                    service_id,
+                },
+            ));
+        }
+        if !remaining.is_zero() {
+            chunks.push((remaining, 0));
+        }
+        if head == len {
+            lots.lots.truncate(0);
+            head = 0;
+        }
+        lots.head.set(U256::from(head));
+        lots.total.set(total);
+
+        self.accessings
+            .setter(holder)
+            .setter(service_id)
+            .set(balance - amount - expired);
+        if !expired.is_zero() {
+            self.burn_lapsed(holder, service_id, expired);
+        }
+        Ok(chunks)
+    }
+
+    /// Adds `amount` accessings lapsing at `expires_at` (zero for never) to
+    /// `holder`, keeping their lots ordered by expiry and merging equal ones.
+    /// With `capped`, fails rather than grow past `MAX_CREDIT_LOTS` live lots;
+    /// only refunds of credits the holder already had skip the cap.
+    fn add_lot(
+        &mut self,
+        holder: Address,
+        service_id: U256,
+        amount: U256,
+        expires_at: u64,
+        capped: bool,
+    ) -> Result<(), ApiAuthorizationError> {
+        let lots = self.credit_lots.getter(holder);
+        let lots = lots.getter(service_id);
+        if capped && lots.lots.len() - lots.head.get().to::<usize>() >= MAX_CREDIT_LOTS {
+            self.take_credits(holder, service_id, U256::ZERO)?;
+        }
+
+        let mut lots = self.credit_lots.setter(holder);
+        let mut lots = lots.setter(service_id);
+        let head = lots.head.get().to::<usize>();
+        let len = lots.lots.len();
+        let mut index = len;
+        while index > head
+            && lot_order(
+                lots.lots
+                    .get(index - 1)
+                    .unwrap()
+                    .expires_at
+                    .get()
+                    .to::<u64>(),
+            ) > lot_order(expires_at)
+        {
+            index -= 1;
+        }
+
+        let merges = index > head
+            && lots
+                .lots
+                .get(index - 1)
+                .unwrap()
+                .expires_at
+                .get()
+                .to::<u64>()
+                == expires_at;
+        if merges {
+            let mut lot = lots.lots.setter(index - 1).unwrap();
+            let merged = lot.amount.get() + amount;
+            lot.amount.set(merged);
+        } else {
+            if capped && len - head >= MAX_CREDIT_LOTS {
+                return Err(ApiAuthorizationError::TooManyCreditLots(
+                    TooManyCreditLots { holder, service_id },
+                ));
+            }
+            lots.lots.grow();
+            for i in (index..len).rev() {
+                let (moved, moved_expiry) = {
+                    let lot = lots.lots.get(i).unwrap();
+                    (lot.amount.get(), lot.expires_at.get())
+                };
+                let mut lot = lots.lots.setter(i + 1).unwrap();
+                lot.amount.set(moved);
+                lot.expires_at.set(moved_expiry);
+            }
+            let mut lot = lots.lots.setter(index).unwrap();
+            lot.amount.set(amount);
+            lot.expires_at.set(U64::from(expires_at));
+        }
+        let total = lots.total.get() + amount;
+        lots.total.set(total);
+
+        let balance = self.accessings.getter(holder).get(service_id) + amount;
+        self.accessings
+            .setter(holder)
+            .setter(service_id)
+            .set(balance);
+        Ok(())
+    }
+
+    /// Removes lapsed accessings, already taken off `holder`'s lots and
+    /// balance, from the supply.
+    fn burn_lapsed(&mut self, holder: Address, service_id: U256, amount: U256) {
+        let supply = self.supplies.get(service_id) - amount;
+        self.supplies.setter(service_id).set(supply);
+        evm::log(CreditsExpired {
+            user: holder,
+            service_id,
+            amount,
+        });
+        self.log_token_transfer(holder, Address::ZERO, service_id, amount);
+    }
+
+    /// Spends `amount` accessings for good, shrinking the service's supply.
+    fn burn(
+        &mut self,
+        address: Address,
+        service_id: U256,
+        amount: U256,
+    ) -> Result<U256, ApiAuthorizationError> {
+        self.take_credits(address, service_id, amount)?;
+        let supply = self.supplies.get(service_id) - amount;
+        self.supplies.setter(service_id).set(supply);
+        self.log_token_transfer(address, Address::ZERO, service_id, amount);
+        Ok(self.balance_of(address, service_id))
+    }
+
+    /// Moves accessings along with their expiries, so passing them on never
+    /// extends their life.
+    fn move_credits(
+        &mut self,
+        from: Address,
+        to: Address,
+        service_id: U256,
+        amount: U256,
+    ) -> Result<(), ApiAuthorizationError> {
+        if to.is_zero() || to == contract::address() {
+            return Err(ApiAuthorizationError::InvalidRecipient(InvalidRecipient {
+                to,
+            }));
+        }
+        for (chunk, expires_at) in self.take_credits(from, service_id, amount)? {
+            self.add_lot(to, service_id, chunk, expires_at, true)?;
+        }
+        evm::log(CreditsTransferred {
+            from,
+            to,
+            service_id,
+            amount,
+        });
+        self.log_token_transfer(from, to, service_id, amount);
+        Ok(())
+    }
+
+    /// Locks `amount` of `user`'s accessings in the contract for
+    /// `session_id`. The session keeps the chunks taken, so settling it hands
+    /// back exactly those with their own expiries.
+    fn escrow_credits(
+        &mut self,
+        session_id: U256,
+        user: Address,
+        service_id: U256,
+        amount: U256,
+    ) -> Result<(), ApiAuthorizationError> {
+        let chunks = self.take_credits(user, service_id, amount)?;
+        let escrow = contract::address();
+        let balance = self.accessings.getter(escrow).get(service_id) + amount;
+        self.accessings
+            .setter(escrow)
+            .setter(service_id)
+            .set(balance);
+
+        let mut session = self.sessions.setter(session_id);
+        for (chunk, expires_at) in chunks {
+            let mut lot = session.lots.grow();
+            lot.amount.set(chunk);
+            lot.expires_at.set(U64::from(expires_at));
+        }
+        evm::log(CreditsTransferred {
+            from: user,
+            to: escrow,
+            service_id,
+            amount,
+        });
+        self.log_token_transfer(user, escrow, service_id, amount);
+        Ok(())
+    }
+
+    /// Settles the escrow of `session_id`: burns `used` accessings out of the
+    /// session's own chunks, soonest-expiring first, and returns the
+    /// rest to the user. Returns the refund.
+    fn release_escrow(
+        &mut self,
+        session_id: U256,
+        used: U256,
+    ) -> Result<U256, ApiAuthorizationError> {
+        let session = self.sessions.getter(session_id);
+        let user = session.user.get();
+        let service_id = session.service_id.get();
+        let deposit = session.deposit.get();
+        let chunks: Vec<(U256, u64)> = (0..session.lots.len())
+            .map(|i| session.lots.get(i).unwrap())
+            .map(|lot| (lot.amount.get(), lot.expires_at.get().to::<u64>()))
+            .collect();
+
+        let escrow = contract::address();
+        let balance = self.accessings.getter(escrow).get(service_id) - deposit;
+        self.accessings
+            .setter(escrow)
+            .setter(service_id)
+            .set(balance);
+
+        let mut unburned = used;
+        for (chunk, expires_at) in chunks {
+            let burned = chunk.min(unburned);
+            unburned -= burned;
+            if burned != chunk {
+                self.add_lot(user, service_id, chunk - burned, expires_at, false)?;
+            }
+        }
+        if !used.is_zero() {
+            let supply = self.supplies.get(service_id) - used;
+            self.supplies.setter(service_id).set(supply);
+            self.log_token_transfer(escrow, Address::ZERO, service_id, used);
+        }
+        let refunded = deposit - used;
+        if !refunded.is_zero() {
+            evm::log(CreditsTransferred {
+                from: escrow,
+                to: user,
+                service_id,
+                amount: refunded,
+            });
+            self.log_token_transfer(escrow, user, service_id, refunded);
+        }
+        Ok(refunded)
+    }
+
+    /// Loads an open session, returning `(user, operator, service_id, deposit,
+    /// expires_at)`.
+    fn open_session_of(
+        &self,
+        session_id: U256,
+    ) -> Result<(Address, Address, U256, U256, u64), ApiAuthorizationError> {
+        let session = self.sessions.getter(session_id);
+        if session.user.get().is_zero() || session.closed.get() {
+            return Err(ApiAuthorizationError::UnknownSession(UnknownSession {
+                session_id,
+            }));
+        }
+        Ok((
+            session.user.get(),
+            session.operator.get(),
+            session.service_id.get(),
+            session.deposit.get(),
+            session.expires_at.get().to::<u64>(),
+        ))
+    }
+
+    /// Counts `calls` served to `user` towards their lifetime and current
+    /// epoch usage, whether paid with credits or a subscription.
+    fn record_usage(&mut self, user: Address, calls: U256) {
+        let epoch = U256::from(block::timestamp() / EPOCH_LENGTH);
+        let mut stats = self.usage_stats.setter(user);
+        let consumed = stats.consumed.get() + calls;
+        stats.consumed.set(consumed);
+        let epoch_usage = stats.epoch_usage.get(epoch) + calls;
+        stats.epoch_usage.setter(epoch).set(epoch_usage);
+    }
+
+    fn set_allowance(&mut self, owner: Address, spender: Address, service_id: U256, amount: U256) {
+        self.allowances
+            .setter(owner)
+            .setter(spender)
+            .setter(service_id)
+            .set(amount);
+        evm::log(CreditsApproval {
+            owner,
+            spender,
+            service_id,
+            amount,
+        });
+        if self.is_token_service(service_id) {
+            evm::log(Approval {
+                owner,
+                spender,
+                value: amount,
+            });
+        }
+    }
+
+    fn spend_allowance(
+        &mut self,
+        owner: Address,
+        spender: Address,
+        service_id: U256,
+        amount: U256,
+    ) -> Result<(), ApiAuthorizationError> {
+        let allowance = self.allowance(owner, spender, service_id);
+        if allowance < amount {
+            return Err(ApiAuthorizationError::InsufficientAllowance(
+                InsufficientAllowance {
+                    owner,
+                    spender,
+                    service_id,
+                },
+            ));
+        }
+        if allowance != U256::MAX {
+            self.allowances
+                .setter(owner)
+                .setter(spender)
+                .setter(service_id)
+                .set(allowance - amount);
+        }
+        Ok(())
+    }
+
+    fn mint(
+        &mut self,
+        address: Address,
+        service_id: U256,
+        amount: U256,
+        expires_at: u64,
+    ) -> Result<(), ApiAuthorizationError> {
+        self.add_lot(address, service_id, amount, expires_at, true)?;
+        let supply = self.supplies.get(service_id) + amount;
+        self.supplies.setter(service_id).set(supply);
+        self.log_token_transfer(Address::ZERO, address, service_id, amount);
+        Ok(())
+    }
+
+    /// Mints `new_accessing` purchased accessings plus a free `bonus`, lapsing
+    /// after the configured credit lifetime. Only the former count as
+    /// purchased. Those of the token service never lapse, since an ERC-20
+    /// balance can't drop without a `Transfer`.
+    fn credit(
+        &mut self,
+        address: Address,
+        service_id: U256,
+        new_accessing: U256,
+        bonus: U256,
+    ) -> Result<U256, ApiAuthorizationError> {
+        let lifetime = self.credit_lifetime.get().to::<u64>();
+        let expires_at = if lifetime == 0 || self.is_token_service(service_id) {
+            0
+        } else {
+            expiry_after(lifetime)?
+        };
+        let accessings = new_accessing + bonus;
+        self.mint(address, service_id, accessings, expires_at)?;
+        let mut stats = self.usage_stats.setter(address);
+        let purchased = stats.purchased.get() + new_accessing;
+        stats.purchased.set(purchased);
+        evm::log(Purchase {
+            addr: address,
+            service_id,
+            accessings,
+        });
+        Ok(self.balance_of(address, service_id))
+    }
 }
 
 #[public]
+#[inherit(Ownable, Pausable, AccessControl)]
 impl ApiAuthorization {
+    /// Also makes purchased accessings lapse after a year until
+    /// `set_credit_lifetime` says otherwise.
+    pub fn initialize(&mut self, owner: Address) -> Result<(), ApiAuthorizationError> {
+        self.ownable.initialize(&mut self.access_control, owner)?;
+        self.credit_lifetime.set(U64::from(DEFAULT_CREDIT_LIFETIME));
+        evm::log(CreditLifetimeSet {
+            lifetime: DEFAULT_CREDIT_LIFETIME,
+        });
+        Ok(())
+    }
+
+    pub fn accept_ownership(&mut self) -> Result<(), ApiAuthorizationError> {
+        Ok(self.ownable.accept_ownership(&mut self.access_control)?)
+    }
+
+    /// Halts purchases, usage metering and credit movements.
+    pub fn pause(&mut self) -> Result<(), ApiAuthorizationError> {
+        Ok(self.pausable.pause(&self.access_control)?)
+    }
+
+    pub fn unpause(&mut self) -> Result<(), ApiAuthorizationError> {
+        Ok(self.pausable.unpause(&self.access_control)?)
+    }
+
+    /// Registers `service_id` or updates its price, in wei per accessing.
+    pub fn register_service(
+        &mut self,
+        service_id: U256,
+        price: U256,
+    ) -> Result<(), ApiAuthorizationError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        if price.is_zero() {
+            return Err(ApiAuthorizationError::InvalidPrice(InvalidPrice {}));
+        }
+
+        if !self.services.getter(service_id).registered.get() {
+            self.services.setter(service_id).registered.set(true);
+            self.service_ids.push(service_id);
+        }
+        self.services.setter(service_id).price.set(price);
+        evm::log(ServiceRegistered { service_id, price });
+        Ok(())
+    }
+
+    pub fn services(&self) -> Vec<U256> {
+        (0..self.service_ids.len())
+            .map(|i| self.service_ids.get(i).unwrap())
+            .collect()
+    }
+
+    pub fn service_price(&self, service_id: U256) -> U256 {
+        self.services.getter(service_id).price.get()
+    }
+
+    /// Replaces the volume discounts of `service_id`. A purchase worth at
+    /// least `min_values[i]` wei is charged `prices[i]` per accessing instead
+    /// of the base price; thresholds must be strictly increasing.
+    pub fn set_price_tiers(
+        &mut self,
+        service_id: U256,
+        min_values: Vec<U256>,
+        prices: Vec<U256>,
+    ) -> Result<(), ApiAuthorizationError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        self.registered_service(service_id)?;
+        if min_values.len() != prices.len()
+            || prices.iter().any(|price| price.is_zero())
+            || min_values.windows(2).any(|pair| pair[0] >= pair[1])
+        {
+            return Err(ApiAuthorizationError::InvalidPriceTiers(
+                InvalidPriceTiers {},
+            ));
+        }
+
+        let mut service = self.services.setter(service_id);
+        service.price_tiers.truncate(0);
+        for (min_value, price) in min_values.iter().zip(&prices) {
+            let mut tier = service.price_tiers.grow();
+            tier.min_value.set(*min_value);
+            tier.price.set(*price);
+        }
+        evm::log(PriceTiersSet {
+            service_id,
+            min_values,
+            prices,
+        });
+        Ok(())
+    }
+
+    /// Returns the `(min_values, prices)` of the volume discounts of
+    /// `service_id`.
+    pub fn price_tiers(&self, service_id: U256) -> (Vec<U256>, Vec<U256>) {
+        let service = self.services.getter(service_id);
+        (0..service.price_tiers.len())
+            .map(|i| {
+                let tier = service.price_tiers.get(i).unwrap();
+                (tier.min_value.get(), tier.price.get())
+            })
+            .unzip()
+    }
+
+    /// Accessings of `service_id` a purchase worth `value` wei buys, bonuses
+    /// from promo codes aside.
+    pub fn quote(&self, service_id: U256, value: U256) -> U256 {
+        if !self.services.getter(service_id).registered.get() {
+            return U256::ZERO;
+        }
+        value / self.price_for(service_id, value)
+    }
+
+    #[payable]
+    pub fn purchase(&mut self, service_id: U256) -> Result<U256, ApiAuthorizationError> {
+        self.pausable.when_not_paused()?;
+        let new_accessing = self.accessings_for_value(service_id)?;
+        self.credit(msg::sender(), service_id, new_accessing, U256::ZERO)
+    }
+
+    /// Lets up to `max_uses` users redeem the promo code hashing to
+    /// `code_hash` (keccak256 of the code) for `bonus` extra accessings on a
+    /// purchase of `service_id` until `expires_at`. Creating an existing code
+    /// again replaces its terms but keeps its redemptions, so setting
+    /// `max_uses` to zero retires it. Redeeming reveals the code on-chain.
+    pub fn create_promo_code(
+        &mut self,
+        code_hash: B256,
+        service_id: U256,
+        bonus: U256,
+        max_uses: U256,
+        expires_at: u64,
+    ) -> Result<(), ApiAuthorizationError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        self.registered_service(service_id)?;
+        if expires_at <= block::timestamp() {
+            return Err(ApiAuthorizationError::InvalidExpiry(InvalidExpiry {
+                expires_at,
+            }));
+        }
+
+        let mut promo_code = self.promo_codes.setter(code_hash);
+        promo_code.service_id.set(service_id);
+        promo_code.bonus.set(bonus);
+        promo_code.max_uses.set(max_uses);
+        promo_code.expires_at.set(U64::from(expires_at));
+        evm::log(PromoCodeCreated {
+            code_hash,
+            service_id,
+            bonus,
+            max_uses,
+            expires_at,
+        });
+        Ok(())
+    }
+
+    /// Returns `(service_id, bonus, max_uses, uses, expires_at)` of the promo
+    /// code hashing to `code_hash`.
+    pub fn promo_code(&self, code_hash: B256) -> (U256, U256, U256, U256, u64) {
+        let promo_code = self.promo_codes.getter(code_hash);
+        (
+            promo_code.service_id.get(),
+            promo_code.bonus.get(),
+            promo_code.max_uses.get(),
+            promo_code.uses.get(),
+            promo_code.expires_at.get().to::<u64>(),
+        )
+    }
+
+    /// Like `purchase`, adding the bonus of promo code `code` on top. Each
+    /// user can redeem a code once, and only on a purchase of at least one
+    /// accessing.
     #[payable]
-    pub fn purchase(&mut self) -> U256 {
-        let new_accessing = msg::value()
-            .checked_div(U256::from(2180330000000000u128))
-            .unwrap();
-        let final_accessing = self
-            .accessings
-            .setter(msg::sender())
-            .checked_add(new_accessing)
-            .unwrap();
-        self.accessings.setter(msg::sender()).set(final_accessing);
-        self.accessings.get(msg::sender())
-    }
-
-    pub fn balance_of(&self, address: Address) -> U256 {
-        self.accessings.get(address)
-    }
-
-    pub fn mark_usage(&mut self, address: Address) -> U256 {
-        let final_accessing = self
-            .accessings
-            .setter(msg::sender())
-            .checked_sub(U256::from(1))
-            .unwrap();
-        self.accessings.setter(msg::sender()).set(final_accessing);
-        self.accessings.get(msg::sender())
+    pub fn purchase_with_code(
+        &mut self,
+        service_id: U256,
+        code: String,
+    ) -> Result<U256, ApiAuthorizationError> {
+        self.pausable.when_not_paused()?;
+        let new_accessing = self.accessings_for_value(service_id)?;
+
+        let code_hash = crypto::keccak(code.as_bytes());
+        let user = msg::sender();
+        let (code_service_id, bonus, max_uses, uses, expires_at) = self.promo_code(code_hash);
+        if code_service_id != service_id || expires_at <= block::timestamp() || uses >= max_uses {
+            return Err(ApiAuthorizationError::InvalidPromoCode(InvalidPromoCode {
+                code_hash,
+            }));
+        }
+        if self.promo_codes.getter(code_hash).redeemed.get(user) {
+            return Err(ApiAuthorizationError::PromoCodeAlreadyRedeemed(
+                PromoCodeAlreadyRedeemed { code_hash, user },
+            ));
+        }
+
+        let mut promo_code = self.promo_codes.setter(code_hash);
+        promo_code.uses.set(uses + U256::from(1));
+        promo_code.redeemed.setter(user).set(true);
+        evm::log(PromoCodeRedeemed {
+            code_hash,
+            user,
+            bonus,
+        });
+        self.credit(user, service_id, new_accessing, bonus)
+    }
+
+    /// Buys accessings for `service_id` with an accepted ERC-20 token. Only the
+    /// whole number of accessings covered by `amount` is pulled from the
+    /// caller, who must have approved this contract beforehand.
+    pub fn purchase_with_token(
+        &mut self,
+        service_id: U256,
+        token: Address,
+        amount: U256,
+    ) -> Result<U256, ApiAuthorizationError> {
+        self.pausable.when_not_paused()?;
+        self.registered_service(service_id)?;
+        let price = self.services.getter(service_id).token_prices.get(token);
+        if price.is_zero() {
+            return Err(ApiAuthorizationError::TokenNotAccepted(TokenNotAccepted {
+                token,
+            }));
+        }
+        let new_accessing = amount / price;
+        if new_accessing.is_zero() {
+            return Err(ApiAuthorizationError::InsufficientPayment(
+                InsufficientPayment { amount, price },
+            ));
+        }
+
+        let cost = new_accessing * price;
+        let transferred = IERC20::new(token)
+            .transfer_from(Call::new_in(self), msg::sender(), contract::address(), cost)
+            .unwrap_or(false);
+        if !transferred {
+            return Err(ApiAuthorizationError::TokenTransferFailed(
+                TokenTransferFailed { token },
+            ));
+        }
+
+        self.credit(msg::sender(), service_id, new_accessing, U256::ZERO)
+    }
+
+    /// Sets the price of one accessing of `service_id` in units of `token`. A
+    /// price of zero stops accepting the token for that service.
+    pub fn set_token_price(
+        &mut self,
+        service_id: U256,
+        token: Address,
+        price: U256,
+    ) -> Result<(), ApiAuthorizationError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        self.registered_service(service_id)?;
+
+        let mut service = self.services.setter(service_id);
+        let listed = (0..service.accepted_tokens.len())
+            .position(|i| service.accepted_tokens.get(i).unwrap() == token);
+        match listed {
+            None if !price.is_zero() => service.accepted_tokens.push(token),
+            Some(i) if price.is_zero() => {
+                let last = service.accepted_tokens.len() - 1;
+                let last_token = service.accepted_tokens.get(last).unwrap();
+                service.accepted_tokens.setter(i).unwrap().set(last_token);
+                service.accepted_tokens.pop();
+            }
+            _ => {}
+        }
+
+        service.token_prices.setter(token).set(price);
+        evm::log(TokenPriceSet {
+            service_id,
+            token,
+            price,
+        });
+        Ok(())
+    }
+
+    pub fn token_price(&self, service_id: U256, token: Address) -> U256 {
+        self.services.getter(service_id).token_prices.get(token)
+    }
+
+    pub fn accepted_tokens(&self, service_id: U256) -> Vec<Address> {
+        let service = self.services.getter(service_id);
+        (0..service.accepted_tokens.len())
+            .map(|i| service.accepted_tokens.get(i).unwrap())
+            .collect()
+    }
+
+    /// Sends `amount` wei of purchase revenue to `to`.
+    pub fn withdraw(&mut self, to: Address, amount: U256) -> Result<(), ApiAuthorizationError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        if to.is_zero() {
+            return Err(ApiAuthorizationError::InvalidRecipient(InvalidRecipient {
+                to,
+            }));
+        }
+        call::transfer_eth(to, amount)
+            .map_err(|_| ApiAuthorizationError::WithdrawalFailed(WithdrawalFailed { to }))?;
+        evm::log(Withdrawn { to, amount });
+        Ok(())
+    }
+
+    /// Sends `amount` of `token` collected by `purchase_with_token` to `to`.
+    pub fn withdraw_token(
+        &mut self,
+        token: Address,
+        to: Address,
+        amount: U256,
+    ) -> Result<(), ApiAuthorizationError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        if to.is_zero() {
+            return Err(ApiAuthorizationError::InvalidRecipient(InvalidRecipient {
+                to,
+            }));
+        }
+        let transferred = IERC20::new(token)
+            .transfer(Call::new_in(self), to, amount)
+            .unwrap_or(false);
+        if !transferred {
+            return Err(ApiAuthorizationError::TokenTransferFailed(
+                TokenTransferFailed { token },
+            ));
+        }
+        evm::log(TokenWithdrawn { token, to, amount });
+        Ok(())
+    }
+
+    /// Creates a subscription plan for `service_id` lasting `duration` seconds.
+    /// A `call_cap` of zero means unlimited calls while the plan is active.
+    pub fn create_plan(
+        &mut self,
+        service_id: U256,
+        price: U256,
+        duration: u64,
+        call_cap: U256,
+    ) -> Result<U256, ApiAuthorizationError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        self.registered_service(service_id)?;
+        if price.is_zero() || duration == 0 {
+            return Err(ApiAuthorizationError::InvalidPrice(InvalidPrice {}));
+        }
+
+        let plan_id = self.plan_count.get();
+        let mut plan = self.plans.setter(plan_id);
+        plan.active.set(true);
+        plan.service_id.set(service_id);
+        plan.price.set(price);
+        plan.duration.set(U64::from(duration));
+        plan.call_cap.set(call_cap);
+        self.plan_count.set(plan_id + U256::from(1));
+
+        evm::log(PlanCreated {
+            plan_id,
+            service_id,
+            price,
+            duration,
+            call_cap,
+        });
+        Ok(plan_id)
+    }
+
+    pub fn set_plan_active(
+        &mut self,
+        plan_id: U256,
+        active: bool,
+    ) -> Result<(), ApiAuthorizationError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        if plan_id >= self.plan_count.get() {
+            return Err(ApiAuthorizationError::UnknownPlan(UnknownPlan { plan_id }));
+        }
+        self.plans.setter(plan_id).active.set(active);
+        evm::log(PlanActiveSet { plan_id, active });
+        Ok(())
+    }
+
+    /// Returns `(service_id, price, duration, call_cap, active)` of `plan_id`.
+    pub fn plan(&self, plan_id: U256) -> (U256, U256, u64, U256, bool) {
+        let plan = self.plans.getter(plan_id);
+        (
+            plan.service_id.get(),
+            plan.price.get(),
+            plan.duration.get().to::<u64>(),
+            plan.call_cap.get(),
+            plan.active.get(),
+        )
+    }
+
+    /// Starts a subscription to `plan_id` for the caller. A service can only
+    /// have one running subscription per user at a time; a new one can start
+    /// once the previous one expired or used up its call cap. Takes exactly
+    /// the plan's price.
+    #[payable]
+    pub fn subscribe(&mut self, plan_id: U256) -> Result<u64, ApiAuthorizationError> {
+        self.pausable.when_not_paused()?;
+        let (service_id, price, duration, call_cap, active) = self.plan(plan_id);
+        if !active {
+            return Err(ApiAuthorizationError::UnknownPlan(UnknownPlan { plan_id }));
+        }
+
+        if msg::value() != price {
+            return Err(ApiAuthorizationError::IncorrectPayment(IncorrectPayment {
+                paid: msg::value(),
+                price,
+            }));
+        }
+        let user = msg::sender();
+        if self.subscription_covers(user, service_id) {
+            return Err(ApiAuthorizationError::SubscriptionActive(
+                SubscriptionActive { user, service_id },
+            ));
+        }
+
+        let expires_at = block::timestamp() + duration;
+        let mut subscriptions = self.subscriptions.setter(user);
+        let mut subscription = subscriptions.setter(service_id);
+        subscription.plan_id.set(plan_id);
+        subscription.expires_at.set(U64::from(expires_at));
+        subscription.call_cap.set(call_cap);
+        subscription.calls.set(U256::ZERO);
+
+        evm::log(Subscribed {
+            user,
+            plan_id,
+            expires_at,
+        });
+        Ok(expires_at)
+    }
+
+    /// Returns `(plan_id, expires_at, calls, call_cap)` of the subscription
+    /// `user` holds for `service_id`.
+    pub fn subscription_of(&self, user: Address, service_id: U256) -> (U256, u64, U256, U256) {
+        let subscriptions = self.subscriptions.getter(user);
+        let subscription = subscriptions.getter(service_id);
+        (
+            subscription.plan_id.get(),
+            subscription.expires_at.get().to::<u64>(),
+            subscription.calls.get(),
+            subscription.call_cap.get(),
+        )
+    }
+
+    pub fn has_active_subscription(&self, user: Address, service_id: U256) -> bool {
+        self.subscription_covers(user, service_id)
+    }
+
+    /// Unexpired accessings of `service_id` held by `address`.
+    pub fn balance_of(&self, address: Address, service_id: U256) -> U256 {
+        let balance = self.accessings.getter(address).get(service_id);
+        let now = block::timestamp();
+        let lots = self.credit_lots.getter(address);
+        let lots = lots.getter(service_id);
+        let mut expired = U256::ZERO;
+        for i in lots.head.get().to::<usize>()..lots.lots.len() {
+            let lot = lots.lots.get(i).unwrap();
+            let expires_at = lot.expires_at.get().to::<u64>();
+            if expires_at == 0 || expires_at > now {
+                break;
+            }
+            expired += lot.amount.get();
+        }
+        balance - expired
+    }
+
+    /// Returns the `(amounts, expiries)` of the lots `address` holds of
+    /// `service_id`, soonest-expiring first. An expiry of zero never lapses; credits
+    /// from before lots existed are not listed.
+    pub fn credit_lots(&self, address: Address, service_id: U256) -> (Vec<U256>, Vec<u64>) {
+        let lots = self.credit_lots.getter(address);
+        let lots = lots.getter(service_id);
+        (lots.head.get().to::<usize>()..lots.lots.len())
+            .map(|i| lots.lots.get(i).unwrap())
+            .filter(|lot| !lot.amount.get().is_zero())
+            .map(|lot| (lot.amount.get(), lot.expires_at.get().to::<u64>()))
+            .unzip()
+    }
+
+    /// Gives `user` `amount` free accessings of `service_id` lapsing at
+    /// `expires_at`, rounded up to a whole week, e.g. as a trial. Callable by
+    /// granters and the admin. Not available for the token service, whose
+    /// credits never lapse.
+    pub fn grant_credits(
+        &mut self,
+        user: Address,
+        service_id: U256,
+        amount: U256,
+        expires_at: u64,
+    ) -> Result<(), ApiAuthorizationError> {
+        self.pausable.when_not_paused()?;
+        if !self.access_control.has_role(GRANTER_ROLE, msg::sender()) {
+            self.only_role(DEFAULT_ADMIN_ROLE)?;
+        }
+        self.registered_service(service_id)?;
+        if user.is_zero() || user == contract::address() {
+            return Err(ApiAuthorizationError::InvalidRecipient(InvalidRecipient {
+                to: user,
+            }));
+        }
+        if expires_at <= block::timestamp() || self.is_token_service(service_id) {
+            return Err(ApiAuthorizationError::InvalidExpiry(InvalidExpiry {
+                expires_at,
+            }));
+        }
+
+        let Some(expires_at) = round_expiry(expires_at) else {
+            return Err(ApiAuthorizationError::InvalidExpiry(InvalidExpiry {
+                expires_at,
+            }));
+        };
+        self.mint(user, service_id, amount, expires_at)?;
+        evm::log(CreditsGranted {
+            user,
+            service_id,
+            amount,
+            expires_at,
+        });
+        Ok(())
+    }
+
+    /// Sets how many seconds purchased accessings stay usable, rounded up to
+    /// the end of a week, zero meaning forever. Only affects later purchases
+    /// of services other than the token service.
+    pub fn set_credit_lifetime(&mut self, lifetime: u64) -> Result<(), ApiAuthorizationError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        expiry_after(lifetime)?;
+        self.credit_lifetime.set(U64::from(lifetime));
+        evm::log(CreditLifetimeSet { lifetime });
+        Ok(())
+    }
+
+    pub fn credit_lifetime(&self) -> u64 {
+        self.credit_lifetime.get().to::<u64>()
+    }
+
+    /// Burns every expired lot of `address`, taking lapsed accessings out of
+    /// the total supply. Anyone may call it; spending credits does the same.
+    pub fn burn_expired(
+        &mut self,
+        address: Address,
+        service_id: U256,
+    ) -> Result<U256, ApiAuthorizationError> {
+        self.pausable.when_not_paused()?;
+        let balance = self.accessings.getter(address).get(service_id);
+        self.take_credits(address, service_id, U256::ZERO)?;
+        Ok(balance - self.accessings.getter(address).get(service_id))
+    }
+
+    /// Spends one accessing of `service_id` from `address`, or counts the call
+    /// against their subscription if one is active. Callable by the user
+    /// themselves or by an operator metering on their behalf.
+    pub fn mark_usage(
+        &mut self,
+        address: Address,
+        service_id: U256,
+    ) -> Result<U256, ApiAuthorizationError> {
+        self.pausable.when_not_paused()?;
+        if msg::sender() != address && !self.access_control.has_role(OPERATOR_ROLE, msg::sender()) {
+            return Err(ApiAuthorizationError::NotAuthorized(NotAuthorized {
+                caller: msg::sender(),
+            }));
+        }
+        self.consume_rate(address)?;
+
+        if self.subscription_covers(address, service_id) {
+            let mut subscriptions = self.subscriptions.setter(address);
+            let mut subscription = subscriptions.setter(service_id);
+            let calls = subscription.calls.get() + U256::from(1);
+            subscription.calls.set(calls);
+            self.record_usage(address, U256::from(1));
+            return Ok(self.balance_of(address, service_id));
+        }
+
+        let remaining = self.burn(address, service_id, U256::from(1))?;
+        self.record_usage(address, U256::from(1));
+        Ok(remaining)
+    }
+
+    /// Caps the `mark_usage` calls of every user without limits of their own
+    /// to `per_window` per `block_window` blocks and `per_hour` per hour.
+    /// Zero disables a limit. Blocks are those the contract sees, which on
+    /// Arbitrum follow L1.
+    pub fn set_default_rate_limit(
+        &mut self,
+        block_window: u64,
+        per_window: U256,
+        per_hour: U256,
+    ) -> Result<(), ApiAuthorizationError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        self.default_rate_limit
+            .set(block_window, per_window, per_hour);
+        evm::log(RateLimitSet {
+            user: Address::ZERO,
+            block_window,
+            per_window,
+            per_hour,
+        });
+        Ok(())
+    }
+
+    /// Gives `user` limits of their own in place of the default ones, e.g.
+    /// higher ones for a backend serving many end users.
+    pub fn set_rate_limit(
+        &mut self,
+        user: Address,
+        block_window: u64,
+        per_window: U256,
+        per_hour: U256,
+    ) -> Result<(), ApiAuthorizationError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        let mut limit = self.rate_limits.setter(user);
+        limit.custom.set(true);
+        limit.set(block_window, per_window, per_hour);
+        evm::log(RateLimitSet {
+            user,
+            block_window,
+            per_window,
+            per_hour,
+        });
+        Ok(())
+    }
+
+    /// Puts `user` back on the default limits.
+    pub fn clear_rate_limit(&mut self, user: Address) -> Result<(), ApiAuthorizationError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        self.rate_limits.setter(user).custom.set(false);
+        let (block_window, per_window, per_hour) = self.default_rate_limit.values();
+        evm::log(RateLimitSet {
+            user,
+            block_window,
+            per_window,
+            per_hour,
+        });
+        Ok(())
+    }
+
+    /// Returns `(block_window, per_window, per_hour)` applying to `user`.
+    pub fn rate_limit(&self, user: Address) -> (u64, U256, U256) {
+        self.rate_limit_of(user)
+    }
+
+    /// Returns the `mark_usage` calls `user` has left in the current block
+    /// window and hour, `U256::MAX` standing for unlimited.
+    pub fn remaining_quota(&self, user: Address) -> (U256, U256) {
+        let (_, per_window, per_hour) = self.rate_limit_of(user);
+        let (window, hour) = self.rate_windows(user);
+        (
+            window.map_or(U256::MAX, |(_, calls)| per_window.saturating_sub(calls)),
+            hour.map_or(U256::MAX, |(_, calls)| per_hour.saturating_sub(calls)),
+        )
+    }
+
+    pub fn epoch_length(&self) -> u64 {
+        EPOCH_LENGTH
+    }
+
+    pub fn current_epoch(&self) -> U256 {
+        U256::from(block::timestamp() / EPOCH_LENGTH)
+    }
+
+    /// Calls `user` made during `epoch`, which covers the `epoch_length()`
+    /// seconds starting at `epoch * epoch_length()`.
+    pub fn usage_in_epoch(&self, user: Address, epoch: U256) -> U256 {
+        self.usage_stats.getter(user).epoch_usage.get(epoch)
+    }
+
+    /// Returns `(purchased, consumed)`: the accessings `user` ever bought
+    /// across all services and the calls they made.
+    pub fn lifetime_stats(&self, user: Address) -> (U256, U256) {
+        let stats = self.usage_stats.getter(user);
+        (stats.purchased.get(), stats.consumed.get())
+    }
+
+    pub fn usage_nonce(&self, user: Address) -> U256 {
+        self.usage_nonces.get(user)
+    }
+
+    pub fn settled_usage(&self, user: Address, service_id: U256) -> U256 {
+        self.settled_usage.getter(user).get(service_id)
+    }
+
+    /// EIP-712 domain separator usage receipts are signed under.
+    pub fn domain_separator(&self) -> B256 {
+        signatures::domain().separator()
+    }
+
+    /// Invalidates every usage receipt the caller signed but which has not
+    /// been settled yet. Already settled usage stays counted.
+    pub fn cancel_usage_receipts(&mut self) -> U256 {
+        let user = msg::sender();
+        let nonce = self.usage_nonces.get(user) + U256::from(1);
+        self.usage_nonces.setter(user).set(nonce);
+        evm::log(UsageNonceIncreased { user, nonce });
+        nonce
+    }
+
+    /// Settles a `UsageReceipt` signed by `user`, charging the calls made since
+    /// the last settled receipt in one go. Receipts are cumulative, so only
+    /// the latest one needs submitting and older ones can't be replayed.
+    pub fn settle_usage(
+        &mut self,
+        user: Address,
+        service_id: U256,
+        used: U256,
+        nonce: U256,
+        signature: Bytes,
+    ) -> Result<U256, ApiAuthorizationError> {
+        self.pausable.when_not_paused()?;
+        self.only_role(OPERATOR_ROLE)?;
+        if nonce != self.usage_nonces.get(user) {
+            return Err(ApiAuthorizationError::InvalidNonce(InvalidNonce { nonce }));
+        }
+        let settled = self.settled_usage.getter(user).get(service_id);
+        if used <= settled {
+            return Err(ApiAuthorizationError::StaleReceipt(StaleReceipt {
+                user,
+                service_id,
+                used,
+            }));
+        }
+
+        let receipt = UsageReceipt {
+            user,
+            serviceId: service_id,
+            used,
+            nonce,
+        };
+        let signer = signatures::recover(signatures::signing_hash(&receipt), &signature);
+        if signer != Some(user) {
+            return Err(ApiAuthorizationError::InvalidSignature(InvalidSignature {}));
+        }
+
+        let charged = used - settled;
+        self.burn(user, service_id, charged)?;
+        self.record_usage(user, charged);
+        self.settled_usage.setter(user).setter(service_id).set(used);
+        evm::log(UsageSettled {
+            user,
+            service_id,
+            used,
+            charged,
+        });
+        Ok(charged)
+    }
+
+    /// Hands `amount` of the caller's `service_id` accessings to `to`.
+    pub fn transfer_credits(
+        &mut self,
+        to: Address,
+        service_id: U256,
+        amount: U256,
+    ) -> Result<bool, ApiAuthorizationError> {
+        self.pausable.when_not_paused()?;
+        self.move_credits(msg::sender(), to, service_id, amount)?;
+        Ok(true)
+    }
+
+    /// Allows `spender` to move up to `amount` of the caller's `service_id`
+    /// accessings with `transfer_credits_from`.
+    pub fn approve(&mut self, spender: Address, service_id: U256, amount: U256) -> bool {
+        self.set_allowance(msg::sender(), spender, service_id, amount);
+        true
+    }
+
+    pub fn allowance(&self, owner: Address, spender: Address, service_id: U256) -> U256 {
+        self.allowances
+            .getter(owner)
+            .getter(spender)
+            .get(service_id)
+    }
+
+    pub fn transfer_credits_from(
+        &mut self,
+        from: Address,
+        to: Address,
+        service_id: U256,
+        amount: U256,
+    ) -> Result<bool, ApiAuthorizationError> {
+        self.pausable.when_not_paused()?;
+        self.spend_allowance(from, msg::sender(), service_id, amount)?;
+        self.move_credits(from, to, service_id, amount)?;
+        Ok(true)
+    }
+
+    /// Picks the service whose accessings are exposed through the ERC-20
+    /// interface below, which reverts until then. Can only be chosen once,
+    /// and only while the service has no supply, so wallet balances stay
+    /// consistent with the `Transfer` events indexers have seen. Its credits
+    /// are minted without expiry for the same reason.
+    pub fn set_token_service(&mut self, service_id: U256) -> Result<(), ApiAuthorizationError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        self.registered_service(service_id)?;
+        if self.token_service_set.get() {
+            return Err(ApiAuthorizationError::TokenServiceAlreadySet(
+                TokenServiceAlreadySet {
+                    service_id: self.token_service.get(),
+                },
+            ));
+        }
+        let supply = self.supplies.get(service_id);
+        if !supply.is_zero() {
+            return Err(ApiAuthorizationError::TokenServiceHasSupply(
+                TokenServiceHasSupply { service_id, supply },
+            ));
+        }
+        self.token_service_set.set(true);
+        self.token_service.set(service_id);
+        evm::log(TokenServiceSet { service_id });
+        Ok(())
+    }
+
+    pub fn token_service(&self) -> U256 {
+        self.token_service.get()
+    }
+
+    pub fn name(&self) -> String {
+        TOKEN_NAME.into()
+    }
+
+    pub fn symbol(&self) -> String {
+        TOKEN_SYMBOL.into()
+    }
+
+    /// Accessings are indivisible, so the token has no decimals.
+    pub fn decimals(&self) -> u8 {
+        0
+    }
+
+    pub fn total_supply(&self) -> Result<U256, ApiAuthorizationError> {
+        Ok(self.supplies.get(self.erc20_service()?))
+    }
+
+    #[selector(name = "balanceOf")]
+    pub fn token_balance_of(&self, account: Address) -> Result<U256, ApiAuthorizationError> {
+        Ok(self.balance_of(account, self.erc20_service()?))
+    }
+
+    pub fn transfer(&mut self, to: Address, value: U256) -> Result<bool, ApiAuthorizationError> {
+        let service_id = self.erc20_service()?;
+        self.transfer_credits(to, service_id, value)
+    }
+
+    #[selector(name = "approve")]
+    pub fn token_approve(
+        &mut self,
+        spender: Address,
+        value: U256,
+    ) -> Result<bool, ApiAuthorizationError> {
+        let service_id = self.erc20_service()?;
+        Ok(self.approve(spender, service_id, value))
+    }
+
+    #[selector(name = "allowance")]
+    pub fn token_allowance(
+        &self,
+        owner: Address,
+        spender: Address,
+    ) -> Result<U256, ApiAuthorizationError> {
+        Ok(self.allowance(owner, spender, self.erc20_service()?))
+    }
+
+    pub fn transfer_from(
+        &mut self,
+        from: Address,
+        to: Address,
+        value: U256,
+    ) -> Result<bool, ApiAuthorizationError> {
+        let service_id = self.erc20_service()?;
+        self.transfer_credits_from(from, to, service_id, value)
+    }
+
+    /// Locks `amount` of the caller's `service_id` accessings for `operator`
+    /// until `expires_at`. The operator serves requests off-chain against the
+    /// user's signed `SessionReceipt` counters and redeems the last one with
+    /// `close_session`.
+    pub fn open_session(
+        &mut self,
+        operator: Address,
+        service_id: U256,
+        amount: U256,
+        expires_at: u64,
+    ) -> Result<U256, ApiAuthorizationError> {
+        self.pausable.when_not_paused()?;
+        if !self.access_control.has_role(OPERATOR_ROLE, operator) {
+            return Err(ApiAuthorizationError::NotAuthorized(NotAuthorized {
+                caller: operator,
+            }));
+        }
+        if expires_at <= block::timestamp() {
+            return Err(ApiAuthorizationError::InvalidExpiry(InvalidExpiry {
+                expires_at,
+            }));
+        }
+
+        let user = msg::sender();
+        let session_id = self.session_count.get();
+        self.escrow_credits(session_id, user, service_id, amount)?;
+
+        let mut session = self.sessions.setter(session_id);
+        session.user.set(user);
+        session.operator.set(operator);
+        session.service_id.set(service_id);
+        session.deposit.set(amount);
+        session.expires_at.set(U64::from(expires_at));
+        self.session_count.set(session_id + U256::from(1));
+
+        evm::log(SessionOpened {
+            session_id,
+            user,
+            operator,
+            service_id,
+            deposit: amount,
+            expires_at,
+        });
+        Ok(session_id)
+    }
+
+    /// Returns `(user, operator, service_id, deposit, expires_at, closed)`.
+    pub fn session(&self, session_id: U256) -> (Address, Address, U256, U256, u64, bool) {
+        let session = self.sessions.getter(session_id);
+        (
+            session.user.get(),
+            session.operator.get(),
+            session.service_id.get(),
+            session.deposit.get(),
+            session.expires_at.get().to::<u64>(),
+            session.closed.get(),
+        )
+    }
+
+    /// Redeems the user's final `SessionReceipt` before the session expires.
+    /// The used accessings are consumed and the rest return to the user.
+    pub fn close_session(
+        &mut self,
+        session_id: U256,
+        used: U256,
+        signature: Bytes,
+    ) -> Result<U256, ApiAuthorizationError> {
+        self.pausable.when_not_paused()?;
+        let (user, operator, _, deposit, expires_at) = self.open_session_of(session_id)?;
+        if msg::sender() != operator {
+            return Err(ApiAuthorizationError::NotAuthorized(NotAuthorized {
+                caller: msg::sender(),
+            }));
+        }
+        if block::timestamp() >= expires_at {
+            return Err(ApiAuthorizationError::SessionExpired(SessionExpired {
+                session_id,
+            }));
+        }
+        if used > deposit {
+            return Err(ApiAuthorizationError::SessionOverdrawn(SessionOverdrawn {
+                session_id,
+                used,
+                deposit,
+            }));
+        }
+        if !used.is_zero() {
+            let receipt = SessionReceipt {
+                sessionId: session_id,
+                used,
+            };
+            let signer = signatures::recover(signatures::signing_hash(&receipt), &signature);
+            if signer != Some(user) {
+                return Err(ApiAuthorizationError::InvalidSignature(InvalidSignature {}));
+            }
+        }
+
+        self.sessions.setter(session_id).closed.set(true);
+        let refunded = self.release_escrow(session_id, used)?;
+        if !used.is_zero() {
+            self.record_usage(user, used);
+        }
+
+        evm::log(SessionClosed {
+            session_id,
+            used,
+            refunded,
+        });
+        Ok(refunded)
+    }
+
+    /// Returns the whole deposit of a session the operator never closed once
+    /// it has expired.
+    pub fn reclaim_session(&mut self, session_id: U256) -> Result<U256, ApiAuthorizationError> {
+        self.pausable.when_not_paused()?;
+        let (_, _, _, deposit, expires_at) = self.open_session_of(session_id)?;
+        if block::timestamp() < expires_at {
+            return Err(ApiAuthorizationError::SessionNotExpired(
+                SessionNotExpired { session_id },
+            ));
+        }
+
+        self.sessions.setter(session_id).closed.set(true);
+        self.release_escrow(session_id, U256::ZERO)?;
+        evm::log(SessionReclaimed {
+            session_id,
+            refunded: deposit,
+        });
+        Ok(deposit)
     }
 }
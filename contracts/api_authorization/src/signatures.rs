@@ -0,0 +1,73 @@
+//! EIP-712 messages signed off-chain by users and checked on-chain through the
+//! `ecrecover` precompile.
+
+use alloc::vec::Vec;
+use alloy_primitives::{address, uint, Address, B256, U256};
+use alloy_sol_types::{eip712_domain, sol, Eip712Domain, SolStruct};
+use stylus_sdk::{
+    block,
+    call::{self, Call},
+    contract,
+};
+
+sol! {
+    /// Cumulative number of `serviceId` calls `user` acknowledges having made.
+    struct UsageReceipt {
+        address user;
+        uint256 serviceId;
+        uint256 used;
+        uint256 nonce;
+    }
+
+    /// Final number of accessings `user` consumed in prepaid session `sessionId`.
+    struct SessionReceipt {
+        uint256 sessionId;
+        uint256 used;
+    }
+}
+
+const ECRECOVER: Address = address!("0000000000000000000000000000000000000001");
+
+/// Half the secp256k1 curve order; larger `s` values are malleable duplicates.
+const SECP256K1N_HALF: U256 =
+    uint!(0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF5D576E7357A4501DDFE92F46681B20A0_U256);
+
+pub fn domain() -> Eip712Domain {
+    eip712_domain! {
+        name: "ApiAuthorization",
+        version: "1",
+        chain_id: block::chainid(),
+        verifying_contract: contract::address(),
+    }
+}
+
+/// Hash a typed message is signed over, bound to this contract and chain.
+pub fn signing_hash<T: SolStruct>(message: &T) -> B256 {
+    message.eip712_signing_hash(&domain())
+}
+
+/// Recovers the signer of `hash` from a 65-byte `r || s || v` signature,
+/// rejecting malformed and malleable signatures.
+pub fn recover(hash: B256, signature: &[u8]) -> Option<Address> {
+    if signature.len() != 65 {
+        return None;
+    }
+    let s = U256::from_be_slice(&signature[32..64]);
+    let v = signature[64];
+    if s > SECP256K1N_HALF || (v != 27 && v != 28) {
+        return None;
+    }
+
+    let mut input = Vec::with_capacity(128);
+    input.extend_from_slice(hash.as_slice());
+    input.extend_from_slice(&[0; 31]);
+    input.push(v);
+    input.extend_from_slice(&signature[..64]);
+
+    let output = call::static_call(Call::new(), ECRECOVER, &input).ok()?;
+    if output.len() != 32 {
+        return None;
+    }
+    let signer = Address::from_slice(&output[12..]);
+    (!signer.is_zero()).then_some(signer)
+}
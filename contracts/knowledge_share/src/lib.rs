@@ -2,112 +2,1056 @@
 #![cfg_attr(not(feature = "export-abi"), no_main)]
 extern crate alloc;
 
-use alloy_primitives::{U256, Address, Uint};
+use core::ops::Range;
+
+use alloy_primitives::{b256, U256, U64, U8, Address, B256, Uint};
 use alloy_sol_types::sol;
-use stylus_sdk::{evm, msg, prelude::*};
+use stylus_common::access_control::{
+    AccessControl, AccessControlBadConfirmation, AccessControlUnauthorizedAccount,
+    DEFAULT_ADMIN_ROLE,
+};
+use stylus_common::erc20::IERC20;
+use stylus_common::ownable::{
+    InvalidInitialization, Ownable, OwnableInvalidOwner, OwnableUnauthorizedAccount,
+};
+use stylus_common::pausable::{EnforcedPause, ExpectedPause, Pausable};
+use stylus_sdk::{block, call::{transfer_eth, Call}, contract, crypto, evm, msg, prelude::*};
+
+/// Submission limit until the owner configures one. The quorum and win
+/// threshold depend on the stake token and have no default.
+const DEFAULT_SUBMISSION_LIMIT: usize = 3;
+/// Longest accepted share, in bytes.
+const MAX_KNOWLEDGE_LENGTH: usize = 512;
+/// Longest title and category of a content submission, in bytes.
+const MAX_TITLE_LENGTH: usize = 80;
+const MAX_CATEGORY_LENGTH: usize = 32;
+/// Marks shares that are the IPFS CID of their content rather than text.
+const CONTENT_PREFIX: &str = "ipfs://";
+/// How long a round takes vote commitments once its submissions are in,
+/// and how long voters then have to reveal them.
+const COMMIT_PERIOD: u64 = 2 * 86_400;
+const REVEAL_PERIOD: u64 = 86_400;
+
+/// keccak256("MODERATOR_ROLE"); may flag submissions.
+const MODERATOR_ROLE: B256 =
+    b256!("71f3d55856e4058ed06ee057d79ada615f65cdf5f9ee88181b914225088f834f");
+/// How long after acceptance a share can be disputed.
+const CHALLENGE_PERIOD: u64 = 3 * 86_400;
 
-const PUSH_LIMIT: usize = 3;
+/// Share statuses reported by the read APIs.
+const SHARE_PENDING: u8 = 0;
+const SHARE_ACCEPTED: u8 = 1;
+const SHARE_REJECTED: u8 = 2;
+const SHARE_FLAGGED: u8 = 3;
+const SHARE_REMOVED: u8 = 4;
+
+/// Basis points in one whole, for the slashing rate.
+const BPS: u64 = 10_000;
+
+/// Round phases.
+const SUBMISSION: u8 = 0;
+const VOTING: u8 = 1;
+const FINALIZED: u8 = 2;
 
 sol! {
     event LimitReached(string[] shares);
+    event KnowledgeSubmitted(uint256 indexed round_id, uint256 indexed index, address indexed submitter);
+    event ContentSubmitted(uint256 indexed round_id, uint256 indexed index, string cid, string title, string category);
+    event VotingStarted(uint256 indexed round_id, uint64 commit_ends_at, uint64 ends_at);
+    event VoteCommitted(uint256 indexed round_id, address indexed voter, bytes32 commitment, uint256 stake);
+    event RoundFinalized(uint256 indexed round_id, bool decided, uint256 winner, address submitter);
+    event RoundStarted(uint256 indexed round_id);
+    event RoundParametersSet(uint256 submission_limit, uint256 quorum, uint256 win_threshold);
+    event RewardParametersSet(uint256 reward_per_share, uint256 submission_fee);
+    event RewardPoolFunded(address indexed from, uint256 amount);
+    event RewardAccrued(address indexed contributor, uint256 indexed round_id, uint256 amount);
+    event RewardsClaimed(address indexed contributor, uint256 amount);
+    event StakingParametersSet(address stake_token, uint256 min_stake, bool quadratic, uint256 slash_bps);
+    event Voted(uint256 indexed round_id, address indexed voter, uint256 index, uint256 stake, uint256 weight);
+    event StakeWithdrawn(uint256 indexed round_id, address indexed voter, uint256 amount);
+    event ShareFlagged(uint256 indexed round_id, uint256 indexed index, address indexed moderator, string reason);
+    event ShareUnflagged(uint256 indexed round_id, uint256 indexed index, address indexed moderator);
+    event KnowledgeDisputed(uint256 indexed knowledge_index, address indexed disputer, string reason);
+    event DisputeResolved(uint256 indexed knowledge_index, bool removed);
+    event KnowledgeRemoved(uint256 indexed knowledge_index);
+    event KnowledgeAccepted(uint256 indexed knowledge_index, uint256 indexed round_id, address indexed submitter, string knowledge);
+
+    error EmptyKnowledge();
+    error KnowledgeTooLong(uint256 length, uint256 max_length);
+    error InvalidCid();
+    error InvalidContentMetadata();
+    error InvalidRoundParameters();
+    error RoundParametersNotSet();
+    error SubmissionsClosed(uint256 round_id);
+    error IncorrectFee(uint256 paid, uint256 fee);
+    error NothingToClaim(address contributor);
+    error RewardTransferFailed(address contributor);
+    error RoundNotInVoting(uint256 round_id);
+    error VotingClosed(uint256 round_id);
+    error RevealNotOpen(uint256 round_id);
+    error NoCommitment(address voter, uint256 round_id);
+    error InvalidReveal(address voter, uint256 round_id);
+    error InvalidShareIndex(uint256 index, uint256 count);
+    error AlreadyVoted(address voter, uint256 round_id);
+    error InvalidStakingParameters();
+    error InsufficientStake(uint256 stake, uint256 min_stake);
+    error InvalidStakeValue(uint256 value, uint256 stake);
+    error StakeTransferFailed(address token);
+    error RoundNotFinalized(uint256 round_id);
+    error NoStake(address voter, uint256 round_id);
+    error UnknownKnowledge(uint256 knowledge_index);
+    error ChallengePeriodOver(uint256 knowledge_index);
+    error AlreadyDisputed(uint256 knowledge_index);
+    error NotDisputed(uint256 knowledge_index);
+    error VotingStillOpen(uint256 round_id, uint64 ends_at);
+}
+
+#[derive(SolidityError)]
+pub enum KnowledgeShareError {
+    EmptyKnowledge(EmptyKnowledge),
+    KnowledgeTooLong(KnowledgeTooLong),
+    InvalidCid(InvalidCid),
+    InvalidContentMetadata(InvalidContentMetadata),
+    InvalidRoundParameters(InvalidRoundParameters),
+    RoundParametersNotSet(RoundParametersNotSet),
+    SubmissionsClosed(SubmissionsClosed),
+    IncorrectFee(IncorrectFee),
+    NothingToClaim(NothingToClaim),
+    RewardTransferFailed(RewardTransferFailed),
+    RoundNotInVoting(RoundNotInVoting),
+    VotingClosed(VotingClosed),
+    RevealNotOpen(RevealNotOpen),
+    NoCommitment(NoCommitment),
+    InvalidReveal(InvalidReveal),
+    InvalidShareIndex(InvalidShareIndex),
+    AlreadyVoted(AlreadyVoted),
+    InvalidStakingParameters(InvalidStakingParameters),
+    InsufficientStake(InsufficientStake),
+    InvalidStakeValue(InvalidStakeValue),
+    StakeTransferFailed(StakeTransferFailed),
+    RoundNotFinalized(RoundNotFinalized),
+    NoStake(NoStake),
+    UnknownKnowledge(UnknownKnowledge),
+    ChallengePeriodOver(ChallengePeriodOver),
+    AlreadyDisputed(AlreadyDisputed),
+    NotDisputed(NotDisputed),
+    VotingStillOpen(VotingStillOpen),
+    EnforcedPause(EnforcedPause),
+    ExpectedPause(ExpectedPause),
+    AccessControlUnauthorizedAccount(AccessControlUnauthorizedAccount),
+    AccessControlBadConfirmation(AccessControlBadConfirmation),
+    InvalidInitialization(InvalidInitialization),
+    OwnableUnauthorizedAccount(OwnableUnauthorizedAccount),
+    OwnableInvalidOwner(OwnableInvalidOwner),
+}
+
+stylus_common::impl_component_errors!(KnowledgeShareError);
+
+/// Indices of the page starting at `offset` with at most `limit` entries of
+/// a list of `len`.
+fn page(len: usize, offset: U256, limit: U256) -> Range<usize> {
+    let len = U256::from(len);
+    let start = offset.min(len);
+    let end = start.saturating_add(limit).min(len);
+    start.to::<usize>()..end.to::<usize>()
+}
+
+/// Whether `cid` looks like an IPFS CID: a base58btc CIDv0 (`Qm...`, 46
+/// characters) or a base32 CIDv1 (`b...`, lowercase).
+fn is_valid_cid(cid: &str) -> bool {
+    const BASE58: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    if let Some(rest) = cid.strip_prefix("Qm") {
+        return cid.len() == 46 && rest.chars().all(|c| BASE58.contains(c));
+    }
+    if let Some(rest) = cid.strip_prefix('b') {
+        return (50..=120).contains(&cid.len())
+            && rest.chars().all(|c| c.is_ascii_lowercase() || ('2'..='7').contains(&c));
+    }
+    false
 }
 
 sol_storage! {
     #[entrypoint]
     pub struct KnowledgeShare {
-        address owner;
+        #[borrow]
+        Ownable ownable;
+        // Single-round state from before rounds existed, kept so the slots
+        // of the fields below stay put.
         bool rewarded;
-
         string[] shares;
         uint256[3] votes;
         mapping(address => uint256) vote_address;
         mapping(uint256 => address) share_address;
+
         mapping(address => uint256) successful_shares;
 
         string[] knowledge;
+
+        #[borrow]
+        Pausable pausable;
+        #[borrow]
+        AccessControl access_control;
+
+        uint256 round_id;
+        mapping(uint256 => Round) rounds;
+
+        uint256 submission_limit;
+        uint256 quorum;
+        uint256 win_threshold;
+
+        uint256 reward_pool;
+        uint256 reward_per_share;
+        uint256 submission_fee;
+        mapping(address => uint256) pending_rewards;
+
+        address stake_token;
+        uint256 min_stake;
+        bool quadratic_voting;
+        uint256 slash_bps;
+
+        mapping(uint256 => KnowledgeRecord) knowledge_records;
+        mapping(address => ShareRef[]) submissions;
+    }
+
+    pub struct ShareRef {
+        uint256 round_id;
+        uint256 index;
+    }
+
+    pub struct Round {
+        uint8 phase;
+        uint64 voting_ends_at;
+        string[] shares;
+        address[] submitters;
+        uint256[] votes;
+        /// Index voted for plus one, zero if the account has not voted.
+        mapping(address => uint256) vote_of;
+        bool decided;
+        uint256 winner;
+        uint256 total_votes;
+        /// Staking terms, fixed when voting opens.
+        address stake_token;
+        bool quadratic;
+        uint256 slash_bps;
+        uint256 total_stake;
+        mapping(uint256 => uint256) stake_on;
+        mapping(address => uint256) stake_of;
+        mapping(address => bool) stake_withdrawn;
+        uint64 commit_ends_at;
+        mapping(address => bytes32) commitments;
+        mapping(uint256 => bool) flagged;
+        uint256 knowledge_index;
+        /// Outcome terms, fixed with the staking terms when voting opens.
+        uint256 quorum;
+        uint256 win_threshold;
+    }
+
+    pub struct KnowledgeRecord {
+        uint256 round_id;
+        address submitter;
+        uint64 accepted_at;
+        uint256 reward;
+        bool disputed;
+        address disputer;
+        bool removed;
     }
 }
 
-#[public]
 impl KnowledgeShare {
-    pub fn set_owner(&mut self) -> bool {
-        if !self.owner.is_zero() {
-            return false;
+    fn only_role(&self, role: B256) -> Result<(), KnowledgeShareError> {
+        Ok(self.access_control.only_role(role)?)
+    }
+
+    /// Returns `(submission_limit, quorum, win_threshold)`, falling back to
+    /// the default submission limit while unset. A zero win threshold means
+    /// the round parameters were never set.
+    fn round_parameters_or_default(&self) -> (U256, U256, U256) {
+        let or = |value: U256, default: U256| if value.is_zero() { default } else { value };
+        (
+            or(self.submission_limit.get(), U256::from(DEFAULT_SUBMISSION_LIMIT)),
+            self.quorum.get(),
+            self.win_threshold.get(),
+        )
+    }
+
+    /// Unflagged share with the most votes of a round that met its quorum,
+    /// the earliest submitted one on a tie, if it got at least its win
+    /// threshold.
+    fn leading_share(&self, round_id: U256) -> Option<U256> {
+        let round = self.rounds.getter(round_id);
+        let quorum = round.quorum.get();
+        let win_threshold = round.win_threshold.get();
+        let total_votes = round.total_votes.get();
+        if win_threshold.is_zero() || total_votes.is_zero() || total_votes < quorum {
+            return None;
         }
-        self.owner.set(msg::sender());
-        self.rewarded.set(true);
-        true
+        let mut leader: Option<(usize, U256)> = None;
+        for i in 0..round.votes.len() {
+            let votes = round.votes.get(i).unwrap();
+            if !round.flagged.get(U256::from(i)) && leader.map_or(true, |(_, most)| votes > most) {
+                leader = Some((i, votes));
+            }
+        }
+        leader
+            .filter(|(_, votes)| *votes >= win_threshold)
+            .map(|(i, _)| U256::from(i))
     }
 
-    pub fn is_reward_in_progress(&self) -> bool {
-        !self.rewarded.get()
+    /// Where share `index` of round `round_id` stands, as a `SHARE_*` status.
+    fn share_status(&self, round_id: U256, index: U256) -> u8 {
+        let round = self.rounds.getter(round_id);
+        if round.decided.get() && round.winner.get() == index {
+            let knowledge_index = round.knowledge_index.get();
+            if self.knowledge_records.getter(knowledge_index).removed.get() {
+                return SHARE_REMOVED;
+            }
+            return SHARE_ACCEPTED;
+        }
+        if round.flagged.get(index) {
+            return SHARE_FLAGGED;
+        }
+        if round.phase.get().to::<u8>() != FINALIZED {
+            return SHARE_PENDING;
+        }
+        SHARE_REJECTED
     }
 
-    pub fn share(&mut self, knowledge: String) {
-        if !self.rewarded.get() {
-            return ();
+    fn phase(&self, round_id: U256) -> u8 {
+        self.rounds.getter(round_id).phase.get().to::<u8>()
+    }
+
+    /// Takes `stake` from the caller in the round's stake token, or checks
+    /// that it came along as ETH when there is none.
+    fn collect_stake(&mut self, token: Address, stake: U256) -> Result<(), KnowledgeShareError> {
+        if token.is_zero() {
+            if msg::value() != stake {
+                return Err(KnowledgeShareError::InvalidStakeValue(InvalidStakeValue { value: msg::value(), stake }));
+            }
+            return Ok(());
+        }
+        if !msg::value().is_zero() {
+            return Err(KnowledgeShareError::InvalidStakeValue(InvalidStakeValue { value: msg::value(), stake }));
+        }
+        let transferred = IERC20::new(token)
+            .transfer_from(Call::new_in(self), msg::sender(), contract::address(), stake)
+            .unwrap_or(false);
+        if !transferred {
+            return Err(KnowledgeShareError::StakeTransferFailed(StakeTransferFailed { token }));
         }
-        let mut new_share = self.shares.grow();
-        new_share.set_str(knowledge);
+        Ok(())
+    }
+
+    fn pay_stake(&mut self, token: Address, to: Address, amount: U256) -> Result<(), KnowledgeShareError> {
+        let paid = if token.is_zero() {
+            transfer_eth(to, amount).is_ok()
+        } else {
+            IERC20::new(token).transfer(Call::new_in(self), to, amount).unwrap_or(false)
+        };
+        if !paid {
+            return Err(KnowledgeShareError::StakeTransferFailed(StakeTransferFailed { token }));
+        }
+        Ok(())
+    }
+
+    /// Counts the revealed vote of `voter` for share `index`, weighing it by
+    /// their committed stake or its square root.
+    fn cast_vote(&mut self, round_id: U256, voter: Address, index: U256) {
+        let mut round = self.rounds.setter(round_id);
+        let stake = round.stake_of.get(voter);
+        let weight = if round.quadratic.get() { stake.root(2) } else { stake };
+
+        let slot = index.to::<usize>();
+        let new_value = round.votes.get(slot).unwrap() + weight;
+        round.votes.setter(slot).unwrap().set(new_value);
+        let total_votes = round.total_votes.get() + weight;
+        round.total_votes.set(total_votes);
+        let stake_on = round.stake_on.get(index) + stake;
+        round.stake_on.setter(index).set(stake_on);
+        round.vote_of
+            .setter(voter)
+            .set(index + U256::from(1));
+        evm::log(Voted {
+            round_id,
+            voter,
+            index,
+            stake,
+            weight
+        });
+    }
+
+    /// What `voter` gets back of their stake in a finalized round: all of it
+    /// when nobody won; backers of the winner also split the slashed part of
+    /// everyone else's stake pro rata, unrevealed votes included. Each
+    /// loser's slash rounds up and each winner's share down, so payouts never
+    /// exceed what was staked.
+    fn stake_payout(&self, round_id: U256, voter: Address) -> U256 {
+        let round = self.rounds.getter(round_id);
+        let stake = round.stake_of.get(voter);
+        if !round.decided.get() {
+            return stake;
+        }
+        let winner = round.winner.get();
+        let winning_stake = round.stake_on.get(winner);
+        let slash_bps = round.slash_bps.get();
+        let slashed = (round.total_stake.get() - winning_stake) * slash_bps / U256::from(BPS);
+        if round.vote_of.get(voter) == winner + U256::from(1) {
+            stake + slashed * stake / winning_stake
+        } else {
+            stake - (stake * slash_bps).div_ceil(U256::from(BPS))
+        }
+    }
+
+    /// Adds `entry` to the running round for the caller, moving the fee into
+    /// the reward pool, and opens voting once the round is full. Returns the
+    /// round and index of the entry.
+    fn submit(&mut self, entry: String) -> Result<(U256, U256), KnowledgeShareError> {
+        self.pausable.when_not_paused()?;
+        let fee = self.submission_fee.get();
+        if msg::value() != fee {
+            return Err(KnowledgeShareError::IncorrectFee(IncorrectFee {
+                paid: msg::value(),
+                fee
+            }));
+        }
+        let round_id = self.round_id.get();
+        if self.phase(round_id) != SUBMISSION {
+            return Err(KnowledgeShareError::SubmissionsClosed(SubmissionsClosed { round_id }));
+        }
+        if self.win_threshold.get().is_zero() {
+            return Err(KnowledgeShareError::RoundParametersNotSet(RoundParametersNotSet {}));
+        }
+
+        if !fee.is_zero() {
+            let reward_pool = self.reward_pool.get() + fee;
+            self.reward_pool.set(reward_pool);
+        }
+
+        let mut round = self.rounds.setter(round_id);
+        let index = U256::from(round.shares.len());
+        round.shares.grow().set_str(entry);
+        round.submitters.push(msg::sender());
+        round.votes.push(U256::ZERO);
+        let submissions = round.shares.len();
+        let mut submission = self.submissions.setter(msg::sender());
+        let mut submission = submission.grow();
+        submission.round_id.set(round_id);
+        submission.index.set(index);
+        evm::log(KnowledgeSubmitted {
+            round_id,
+            index,
+            submitter: msg::sender()
+        });
+
+        let (submission_limit, _, _) = self.round_parameters_or_default();
+        if U256::from(submissions) >= submission_limit {
+            self.start_voting(round_id);
+        }
+        Ok((round_id, index))
+    }
+
+    /// Closes submissions of the current round and opens it for votes.
+    fn start_voting(&mut self, round_id: U256) {
+        let commit_ends_at = block::timestamp() + COMMIT_PERIOD;
+        let ends_at = commit_ends_at + REVEAL_PERIOD;
+        let mut round = self.rounds.setter(round_id);
+        round.phase.set(U8::from(VOTING));
+        round.commit_ends_at.set(U64::from(commit_ends_at));
+        round.voting_ends_at.set(U64::from(ends_at));
+        round.stake_token.set(self.stake_token.get());
+        round.quadratic.set(self.quadratic_voting.get());
+        round.slash_bps.set(self.slash_bps.get());
+        round.quorum.set(self.quorum.get());
+        round.win_threshold.set(self.win_threshold.get());
+        let shares = (0..round.shares.len())
+            .map(|i| round.shares.get(i).unwrap().get_string())
+            .collect();
+
+        evm::log(LimitReached { shares });
+        evm::log(VotingStarted { round_id, commit_ends_at, ends_at });
+    }
+
+    /// Archives the current round with `winner`, if any, adds the winning
+    /// share to `knowledge`, credits its submitter and starts the next round.
+    fn finalize_round(&mut self, round_id: U256, winner: Option<U256>) {
+        let accepted = {
+            let mut round = self.rounds.setter(round_id);
+            round.phase.set(U8::from(FINALIZED));
+            winner.map(|winner| {
+                round.decided.set(true);
+                round.winner.set(winner);
+                let index = winner.to::<usize>();
+                (round.submitters.get(index).unwrap(), round.shares.get(index).unwrap().get_string())
+            })
+        };
+        let submitter = accepted.as_ref().map_or(Address::ZERO, |(submitter, _)| *submitter);
+        if let Some((submitter, knowledge)) = accepted {
+            let new_successful_shares = self.successful_shares.get(submitter) + U256::from(1);
+            self.successful_shares.setter(submitter).set(new_successful_shares);
 
-        if self.shares.len() >= PUSH_LIMIT {
-            self.rewarded.set(false);
-            let mut local_shares: Vec<String> = Vec::new();
-            for share in 0..self.shares.len() {
-                local_shares.push(self.shares.get(share).unwrap().get_string());
-                self.votes.setter(share).unwrap().set(U256::from(0));
+            let amount = self.reward_per_share.get().min(self.reward_pool.get());
+            if !amount.is_zero() {
+                let reward_pool = self.reward_pool.get() - amount;
+                self.reward_pool.set(reward_pool);
+                let pending = self.pending_rewards.get(submitter) + amount;
+                self.pending_rewards.setter(submitter).set(pending);
+                evm::log(RewardAccrued {
+                    contributor: submitter,
+                    round_id,
+                    amount
+                });
             }
 
-            evm::log(LimitReached {
-                shares: local_shares
+            let knowledge_index = U256::from(self.knowledge.len());
+            self.knowledge.grow().set_str(&knowledge);
+            self.rounds.setter(round_id).knowledge_index.set(knowledge_index);
+            let mut record = self.knowledge_records.setter(knowledge_index);
+            record.round_id.set(round_id);
+            record.submitter.set(submitter);
+            record.accepted_at.set(U64::from(block::timestamp()));
+            record.reward.set(amount);
+            evm::log(KnowledgeAccepted {
+                knowledge_index,
+                round_id,
+                submitter,
+                knowledge
             });
         }
+        evm::log(RoundFinalized {
+            round_id,
+            decided: winner.is_some(),
+            winner: winner.unwrap_or_default(),
+            submitter
+        });
+
+        let next_round_id = round_id + U256::from(1);
+        self.round_id.set(next_round_id);
+        evm::log(RoundStarted { round_id: next_round_id });
     }
+}
 
-    pub fn get_submitted_knowledge(&self) -> (Vec<Address>, Vec<String>) {
-        let mut local_shares: Vec<String> = Vec::new();
-        let mut addresses: Vec<Address> = Vec::new();
-        for share in 0..self.shares.len() {
-            addresses.push(self.share_address.get(U256::from(share)));
-            local_shares.push(self.shares.get(share).unwrap().get_string());
+#[public]
+#[inherit(Ownable, Pausable, AccessControl)]
+impl KnowledgeShare {
+    pub fn initialize(&mut self, owner: Address) -> Result<(), KnowledgeShareError> {
+        Ok(self.ownable.initialize(&mut self.access_control, owner)?)
+    }
+
+    pub fn accept_ownership(&mut self) -> Result<(), KnowledgeShareError> {
+        Ok(self.ownable.accept_ownership(&mut self.access_control)?)
+    }
+
+    /// Halts sharing and voting.
+    pub fn pause(&mut self) -> Result<(), KnowledgeShareError> {
+        Ok(self.pausable.pause(&self.access_control)?)
+    }
+
+    pub fn unpause(&mut self) -> Result<(), KnowledgeShareError> {
+        Ok(self.pausable.unpause(&self.access_control)?)
+    }
+
+    /// Whether the current round has stopped taking submissions.
+    pub fn is_reward_in_progress(&self) -> bool {
+        self.phase(self.round_id.get()) != SUBMISSION
+    }
+
+    /// Returns `(round_id, phase, commit_ends_at, voting_ends_at)` of the
+    /// running round, with phase 0 for submission and 1 for voting. Votes are
+    /// committed until `commit_ends_at` and revealed until `voting_ends_at`.
+    pub fn current_round(&self) -> (U256, u8, u64, u64) {
+        let round_id = self.round_id.get();
+        let round = self.rounds.getter(round_id);
+        (
+            round_id,
+            round.phase.get().to::<u8>(),
+            round.commit_ends_at.get().to::<u64>(),
+            round.voting_ends_at.get().to::<u64>(),
+        )
+    }
+
+    /// Returns `(phase, decided, winner, submitters, shares, votes)` of round
+    /// `round_id`; `winner` only means something once `decided`.
+    pub fn round_results(&self, round_id: U256) -> (u8, bool, U256, Vec<Address>, Vec<String>, Vec<U256>) {
+        let round = self.rounds.getter(round_id);
+        let len = round.shares.len();
+        (
+            round.phase.get().to::<u8>(),
+            round.decided.get(),
+            round.winner.get(),
+            (0..len).map(|i| round.submitters.get(i).unwrap()).collect(),
+            (0..len).map(|i| round.shares.get(i).unwrap().get_string()).collect(),
+            (0..len).map(|i| round.votes.get(i).unwrap()).collect(),
+        )
+    }
+
+    /// Sets how many shares close a round's submissions, the vote weight a
+    /// round needs to produce a winner and the weight the winner needs.
+    /// Weights are in units of the stake token (wei when staking ETH), or
+    /// their square root with quadratic voting, so they have to be set, and
+    /// revisited with `set_staking_parameters`, before anything can be
+    /// shared. Rounds already voting keep the values they opened with.
+    pub fn set_round_parameters(&mut self, submission_limit: U256, quorum: U256, win_threshold: U256) -> Result<(), KnowledgeShareError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        if submission_limit.is_zero() || win_threshold.is_zero() {
+            return Err(KnowledgeShareError::InvalidRoundParameters(InvalidRoundParameters {}));
         }
+        self.submission_limit.set(submission_limit);
+        self.quorum.set(quorum);
+        self.win_threshold.set(win_threshold);
+        evm::log(RoundParametersSet {
+            submission_limit,
+            quorum,
+            win_threshold
+        });
+        Ok(())
+    }
+
+    /// Returns `(submission_limit, quorum, win_threshold)`.
+    pub fn round_parameters(&self) -> (U256, U256, U256) {
+        self.round_parameters_or_default()
+    }
+
+    pub fn knowledge_count(&self) -> U256 {
+        U256::from(self.knowledge.len())
+    }
+
+    /// Accepted knowledge from `offset` on, at most `limit` entries. Entries
+    /// removed after a dispute come back empty.
+    pub fn get_knowledge(&self, offset: U256, limit: U256) -> Vec<String> {
+        page(self.knowledge.len(), offset, limit)
+            .map(|i| self.knowledge.get(i).unwrap().get_string())
+            .collect()
+    }
+
+    /// Returns `(submitters, shares, votes)` of the shares of round
+    /// `round_id` from `offset` on, at most `limit` of them.
+    pub fn round_shares(&self, round_id: U256, offset: U256, limit: U256) -> (Vec<Address>, Vec<String>, Vec<U256>) {
+        let round = self.rounds.getter(round_id);
+        let mut submitters = Vec::new();
+        let mut shares = Vec::new();
+        let mut votes = Vec::new();
+        for i in page(round.shares.len(), offset, limit) {
+            submitters.push(round.submitters.get(i).unwrap());
+            shares.push(round.shares.get(i).unwrap().get_string());
+            votes.push(round.votes.get(i).unwrap());
+        }
+        (submitters, shares, votes)
+    }
+
+    /// Indices of the shares of round `round_id` with `status` (0 pending,
+    /// 1 accepted, 2 rejected, 3 flagged, 4 removed), paginated over the
+    /// matches.
+    pub fn round_shares_by_status(&self, round_id: U256, status: u8, offset: U256, limit: U256) -> Vec<U256> {
+        let count = self.rounds.getter(round_id).shares.len();
+        let matches: Vec<U256> = (0..count)
+            .map(U256::from)
+            .filter(|index| self.share_status(round_id, *index) == status)
+            .collect();
+        page(matches.len(), offset, limit).map(|i| matches[i]).collect()
+    }
+
+    /// Returns `(round_ids, indices)` of the shares `submitter` made from
+    /// `offset` on, at most `limit` of them, oldest first.
+    pub fn submissions_of(&self, submitter: Address, offset: U256, limit: U256) -> (Vec<U256>, Vec<U256>) {
+        let submissions = self.submissions.getter(submitter);
+        page(submissions.len(), offset, limit)
+            .map(|i| {
+                let submission = submissions.get(i).unwrap();
+                (submission.round_id.get(), submission.index.get())
+            })
+            .unzip()
+    }
+
+    /// Returns `(submitter, share, votes, stake, status, flagged)` of share
+    /// `index` of round `round_id`, `stake` being what its voters staked.
+    pub fn share_details(&self, round_id: U256, index: U256) -> Result<(Address, String, U256, U256, u8, bool), KnowledgeShareError> {
+        let round = self.rounds.getter(round_id);
+        let count = U256::from(round.shares.len());
+        if index >= count {
+            return Err(KnowledgeShareError::InvalidShareIndex(InvalidShareIndex { index, count }));
+        }
+        let slot = index.to::<usize>();
+        Ok((
+            round.submitters.get(slot).unwrap(),
+            round.shares.get(slot).unwrap().get_string(),
+            round.votes.get(slot).unwrap(),
+            round.stake_on.get(index),
+            self.share_status(round_id, index),
+            round.flagged.get(index),
+        ))
+    }
+
+    /// Returns `(submitted, accepted)`: the shares `contributor` submitted and
+    /// those accepted into the knowledge, less any removed after a dispute.
+    pub fn contributor_stats(&self, contributor: Address) -> (U256, U256) {
+        (
+            U256::from(self.submissions.getter(contributor).len()),
+            self.successful_shares.get(contributor),
+        )
+    }
+
+    /// Sets the ETH paid out of the pool to the submitter of each accepted
+    /// share, and the fee charged per submission, which goes into the pool.
+    pub fn set_reward_parameters(&mut self, reward_per_share: U256, submission_fee: U256) -> Result<(), KnowledgeShareError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        self.reward_per_share.set(reward_per_share);
+        self.submission_fee.set(submission_fee);
+        evm::log(RewardParametersSet {
+            reward_per_share,
+            submission_fee
+        });
+        Ok(())
+    }
+
+    /// Returns `(reward_pool, reward_per_share, submission_fee)`.
+    pub fn reward_parameters(&self) -> (U256, U256, U256) {
+        (self.reward_pool.get(), self.reward_per_share.get(), self.submission_fee.get())
+    }
+
+    /// Adds the ETH sent along to the reward pool.
+    #[payable]
+    pub fn fund_reward_pool(&mut self) {
+        let reward_pool = self.reward_pool.get() + msg::value();
+        self.reward_pool.set(reward_pool);
+        evm::log(RewardPoolFunded {
+            from: msg::sender(),
+            amount: msg::value()
+        });
+    }
+
+    pub fn pending_rewards(&self, contributor: Address) -> U256 {
+        self.pending_rewards.get(contributor)
+    }
+
+    /// Pays out everything the caller earned with accepted shares.
+    pub fn claim_rewards(&mut self) -> Result<U256, KnowledgeShareError> {
+        self.pausable.when_not_paused()?;
+        let contributor = msg::sender();
+        let amount = self.pending_rewards.get(contributor);
+        if amount.is_zero() {
+            return Err(KnowledgeShareError::NothingToClaim(NothingToClaim { contributor }));
+        }
+        self.pending_rewards.setter(contributor).set(U256::ZERO);
+        transfer_eth(contributor, amount)
+            .map_err(|_| KnowledgeShareError::RewardTransferFailed(RewardTransferFailed { contributor }))?;
+        evm::log(RewardsClaimed { contributor, amount });
+        Ok(amount)
+    }
+
+    /// Submits `knowledge` to the running round, paying the submission fee.
+    #[payable]
+    pub fn share(&mut self, knowledge: String) -> Result<(), KnowledgeShareError> {
+        if knowledge.trim().is_empty() {
+            return Err(KnowledgeShareError::EmptyKnowledge(EmptyKnowledge {}));
+        }
+        if knowledge.len() > MAX_KNOWLEDGE_LENGTH {
+            return Err(KnowledgeShareError::KnowledgeTooLong(KnowledgeTooLong {
+                length: U256::from(knowledge.len()),
+                max_length: U256::from(MAX_KNOWLEDGE_LENGTH)
+            }));
+        }
+        if knowledge.starts_with(CONTENT_PREFIX) {
+            return Err(KnowledgeShareError::InvalidCid(InvalidCid {}));
+        }
+        self.submit(knowledge)?;
+        Ok(())
+    }
+
+    /// Submits knowledge too long to store on-chain by its IPFS `cid`. Only
+    /// the CID is kept, as an `ipfs://` URI in place of the text; `title` and
+    /// `category` are only logged.
+    #[payable]
+    pub fn share_content(&mut self, cid: String, title: String, category: String) -> Result<(), KnowledgeShareError> {
+        if !is_valid_cid(&cid) {
+            return Err(KnowledgeShareError::InvalidCid(InvalidCid {}));
+        }
+        if title.trim().is_empty() || title.len() > MAX_TITLE_LENGTH || category.len() > MAX_CATEGORY_LENGTH {
+            return Err(KnowledgeShareError::InvalidContentMetadata(InvalidContentMetadata {}));
+        }
+        let (round_id, index) = self.submit(format!("{CONTENT_PREFIX}{cid}"))?;
+        evm::log(ContentSubmitted {
+            round_id,
+            index,
+            cid,
+            title,
+            category
+        });
+        Ok(())
+    }
+
+    pub fn get_submitted_knowledge(&self) -> (Vec<Address>, Vec<String>) {
+        let (_, _, _, addresses, local_shares, _) = self.round_results(self.round_id.get());
         (addresses, local_shares)
     }
 
+    /// Index the caller voted for in the running round plus one, or zero.
     pub fn get_vote(&self) -> Uint<256, 4> {
-        self.vote_address.get(msg::sender())
-    }
-
-    pub fn vote(&mut self, index: U256) {
-        if
-            self.rewarded.get() ||
-            index.lt(&U256::from(0)) ||
-            index.gt(&U256::from(PUSH_LIMIT)) ||
-            !self.vote_address.get(msg::sender()).eq(&U256::from(0))
-        {
-            return ();
-        }
-
-        let new_value = self.votes
-            .setter(index)
-            .unwrap()
-            .checked_add(U256::from(1))
-            .unwrap();
-        self.votes.setter(index).unwrap().set(new_value);
-        self.vote_address
-            .setter(msg::sender())
-            .set(index.checked_add(U256::from(1)).unwrap());
-        if self.votes.get(index).unwrap() > U256::from(PUSH_LIMIT) {
-            let new_successful_shares = self
-                .successful_shares
-                .setter(self.share_address.get(U256::from(index)))
-                .checked_add(U256::from(1))
-                .unwrap();
-            self
-                .successful_shares
-                .setter(self.share_address.get(U256::from(index)))
-                .set(new_successful_shares);
-            self.rewarded.set(false);
+        self.rounds.getter(self.round_id.get()).vote_of.get(msg::sender())
+    }
+
+    /// Commits to a vote in the running round without disclosing it, as
+    /// `keccak256(abi.encodePacked(uint256 index, bytes32 salt, address voter,
+    /// uint256 round_id, address contract))`, so nobody can replay another
+    /// voter's commitment or one made elsewhere. Locks `stake` sent as ETH
+    /// or pulled in the configured token until the round is finalized.
+    /// Unrevealed commitments count for nothing.
+    #[payable]
+    pub fn commit_vote(&mut self, commitment: B256, stake: U256) -> Result<(), KnowledgeShareError> {
+        self.pausable.when_not_paused()?;
+        let (round_id, phase, commit_ends_at, _) = self.current_round();
+        let voter = msg::sender();
+        if phase != VOTING {
+            return Err(KnowledgeShareError::RoundNotInVoting(RoundNotInVoting { round_id }));
+        }
+        if block::timestamp() >= commit_ends_at {
+            return Err(KnowledgeShareError::VotingClosed(VotingClosed { round_id }));
+        }
+        let round = self.rounds.getter(round_id);
+        if !round.stake_of.get(voter).is_zero() {
+            return Err(KnowledgeShareError::AlreadyVoted(AlreadyVoted { voter, round_id }));
+        }
+        let min_stake = self.min_stake.get();
+        if stake.is_zero() || stake < min_stake {
+            return Err(KnowledgeShareError::InsufficientStake(InsufficientStake { stake, min_stake }));
+        }
+
+        let token = round.stake_token.get();
+        self.collect_stake(token, stake)?;
+        let mut round = self.rounds.setter(round_id);
+        round.commitments.setter(voter).set(commitment);
+        round.stake_of.setter(voter).set(stake);
+        let total_stake = round.total_stake.get() + stake;
+        round.total_stake.set(total_stake);
+        evm::log(VoteCommitted {
+            round_id,
+            voter,
+            commitment,
+            stake
+        });
+        Ok(())
+    }
+
+    /// Opens the caller's commitment once committing is over, counting their
+    /// vote for share `index`.
+    pub fn reveal_vote(&mut self, index: U256, salt: B256) -> Result<(), KnowledgeShareError> {
+        self.pausable.when_not_paused()?;
+        let (round_id, phase, commit_ends_at, ends_at) = self.current_round();
+        let voter = msg::sender();
+        if phase != VOTING || block::timestamp() < commit_ends_at {
+            return Err(KnowledgeShareError::RevealNotOpen(RevealNotOpen { round_id }));
+        }
+        if block::timestamp() >= ends_at {
+            return Err(KnowledgeShareError::VotingClosed(VotingClosed { round_id }));
+        }
+        let round = self.rounds.getter(round_id);
+        if round.stake_of.get(voter).is_zero() {
+            return Err(KnowledgeShareError::NoCommitment(NoCommitment { voter, round_id }));
+        }
+        if !round.vote_of.get(voter).is_zero() {
+            return Err(KnowledgeShareError::AlreadyVoted(AlreadyVoted { voter, round_id }));
+        }
+        let count = U256::from(round.votes.len());
+        if index >= count {
+            return Err(KnowledgeShareError::InvalidShareIndex(InvalidShareIndex { index, count }));
+        }
+        let mut preimage = [0u8; 136];
+        preimage[..32].copy_from_slice(&index.to_be_bytes::<32>());
+        preimage[32..64].copy_from_slice(salt.as_slice());
+        preimage[64..84].copy_from_slice(voter.as_slice());
+        preimage[84..116].copy_from_slice(&round_id.to_be_bytes::<32>());
+        preimage[116..].copy_from_slice(contract::address().as_slice());
+        if crypto::keccak(preimage) != round.commitments.get(voter) {
+            return Err(KnowledgeShareError::InvalidReveal(InvalidReveal { voter, round_id }));
+        }
+
+        self.cast_vote(round_id, voter, index);
+        Ok(())
+    }
+
+    /// Sets the token votes are staked in (zero for ETH), the minimum stake,
+    /// whether votes weigh by the square root of their stake, and the share
+    /// of a losing stake, in basis points, handed to the winner's backers.
+    /// Applies from the next round that opens for votes.
+    pub fn set_staking_parameters(&mut self, stake_token: Address, min_stake: U256, quadratic: bool, slash_bps: U256) -> Result<(), KnowledgeShareError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        if slash_bps > U256::from(BPS) {
+            return Err(KnowledgeShareError::InvalidStakingParameters(InvalidStakingParameters {}));
+        }
+        self.stake_token.set(stake_token);
+        self.min_stake.set(min_stake);
+        self.quadratic_voting.set(quadratic);
+        self.slash_bps.set(slash_bps);
+        evm::log(StakingParametersSet {
+            stake_token,
+            min_stake,
+            quadratic,
+            slash_bps
+        });
+        Ok(())
+    }
+
+    /// Returns `(stake_token, min_stake, quadratic, slash_bps)`.
+    pub fn staking_parameters(&self) -> (Address, U256, bool, U256) {
+        (self.stake_token.get(), self.min_stake.get(), self.quadratic_voting.get(), self.slash_bps.get())
+    }
+
+    /// Returns `(stake, payout, withdrawn)` of `voter` in round `round_id`;
+    /// the payout is only final once the round is.
+    pub fn stake_of(&self, round_id: U256, voter: Address) -> (U256, U256, bool) {
+        let round = self.rounds.getter(round_id);
+        (round.stake_of.get(voter), self.stake_payout(round_id, voter), round.stake_withdrawn.get(voter))
+    }
+
+    /// Returns the caller's stake in finalized round `round_id`, after
+    /// slashing or rewards.
+    pub fn withdraw_stake(&mut self, round_id: U256) -> Result<U256, KnowledgeShareError> {
+        self.pausable.when_not_paused()?;
+        let voter = msg::sender();
+        if self.phase(round_id) != FINALIZED {
+            return Err(KnowledgeShareError::RoundNotFinalized(RoundNotFinalized { round_id }));
+        }
+        let round = self.rounds.getter(round_id);
+        if round.stake_of.get(voter).is_zero() || round.stake_withdrawn.get(voter) {
+            return Err(KnowledgeShareError::NoStake(NoStake { voter, round_id }));
+        }
+        let token = round.stake_token.get();
+        let amount = self.stake_payout(round_id, voter);
+
+        self.rounds.setter(round_id).stake_withdrawn.setter(voter).set(true);
+        if !amount.is_zero() {
+            self.pay_stake(token, voter, amount)?;
+        }
+        evm::log(StakeWithdrawn {
+            round_id,
+            voter,
+            amount
+        });
+        Ok(amount)
+    }
+
+    /// Marks share `index` of round `round_id` as spam or false, keeping it
+    /// from winning. Moderators only.
+    pub fn flag_share(&mut self, round_id: U256, index: U256, reason: String) -> Result<(), KnowledgeShareError> {
+        self.only_role(MODERATOR_ROLE)?;
+        let count = U256::from(self.rounds.getter(round_id).shares.len());
+        if index >= count {
+            return Err(KnowledgeShareError::InvalidShareIndex(InvalidShareIndex { index, count }));
+        }
+        self.rounds.setter(round_id).flagged.setter(index).set(true);
+        evm::log(ShareFlagged {
+            round_id,
+            index,
+            moderator: msg::sender(),
+            reason
+        });
+        Ok(())
+    }
+
+    pub fn unflag_share(&mut self, round_id: U256, index: U256) -> Result<(), KnowledgeShareError> {
+        self.only_role(MODERATOR_ROLE)?;
+        let count = U256::from(self.rounds.getter(round_id).shares.len());
+        if index >= count {
+            return Err(KnowledgeShareError::InvalidShareIndex(InvalidShareIndex { index, count }));
+        }
+        self.rounds.setter(round_id).flagged.setter(index).set(false);
+        evm::log(ShareUnflagged {
+            round_id,
+            index,
+            moderator: msg::sender()
+        });
+        Ok(())
+    }
+
+    /// Returns `(round_id, submitter, accepted_at, disputed, removed)` of
+    /// accepted knowledge `knowledge_index`; `disputed` while a dispute is
+    /// pending.
+    pub fn knowledge_record(&self, knowledge_index: U256) -> (U256, Address, u64, bool, bool) {
+        let record = self.knowledge_records.getter(knowledge_index);
+        (
+            record.round_id.get(),
+            record.submitter.get(),
+            record.accepted_at.get().to::<u64>(),
+            record.disputed.get(),
+            record.removed.get(),
+        )
+    }
+
+    /// Challenges accepted knowledge `knowledge_index` within the challenge
+    /// period, leaving it to the admin to keep or remove it. Open to anyone
+    /// who staked in the round that accepted it, once per entry.
+    pub fn dispute(&mut self, knowledge_index: U256, reason: String) -> Result<(), KnowledgeShareError> {
+        self.pausable.when_not_paused()?;
+        if knowledge_index >= U256::from(self.knowledge.len()) {
+            return Err(KnowledgeShareError::UnknownKnowledge(UnknownKnowledge { knowledge_index }));
+        }
+        let disputer = msg::sender();
+        let (round_id, _, accepted_at, _, _) = self.knowledge_record(knowledge_index);
+        if self.rounds.getter(round_id).stake_of.get(disputer).is_zero() {
+            return Err(KnowledgeShareError::NoStake(NoStake { voter: disputer, round_id }));
+        }
+        if block::timestamp() >= accepted_at + CHALLENGE_PERIOD {
+            return Err(KnowledgeShareError::ChallengePeriodOver(ChallengePeriodOver { knowledge_index }));
+        }
+        if !self.knowledge_records.getter(knowledge_index).disputer.get().is_zero() {
+            return Err(KnowledgeShareError::AlreadyDisputed(AlreadyDisputed { knowledge_index }));
+        }
+
+        let mut record = self.knowledge_records.setter(knowledge_index);
+        record.disputed.set(true);
+        record.disputer.set(disputer);
+        evm::log(KnowledgeDisputed {
+            knowledge_index,
+            disputer,
+            reason
+        });
+        Ok(())
+    }
+
+    /// Settles the dispute over `knowledge_index`. Removing it blanks the
+    /// entry, keeping later indices stable, takes back the submitter's win
+    /// and returns their reward to the pool as far as it is unclaimed.
+    pub fn resolve_dispute(&mut self, knowledge_index: U256, remove: bool) -> Result<(), KnowledgeShareError> {
+        self.only_role(DEFAULT_ADMIN_ROLE)?;
+        let (_, submitter, _, disputed, _) = self.knowledge_record(knowledge_index);
+        if !disputed {
+            return Err(KnowledgeShareError::NotDisputed(NotDisputed { knowledge_index }));
+        }
+        let reward = self.knowledge_records.getter(knowledge_index).reward.get();
+        let mut record = self.knowledge_records.setter(knowledge_index);
+        record.disputed.set(false);
+        record.removed.set(remove);
+
+        if remove {
+            self.knowledge.setter(knowledge_index.to::<usize>()).unwrap().set_str("");
+            let new_successful_shares = self.successful_shares.get(submitter).saturating_sub(U256::from(1));
+            self.successful_shares.setter(submitter).set(new_successful_shares);
+            let pending = self.pending_rewards.get(submitter);
+            let clawed_back = reward.min(pending);
+            self.pending_rewards.setter(submitter).set(pending - clawed_back);
+            let reward_pool = self.reward_pool.get() + clawed_back;
+            self.reward_pool.set(reward_pool);
+            evm::log(KnowledgeRemoved { knowledge_index });
+        }
+        evm::log(DisputeResolved {
+            knowledge_index,
+            removed: remove
+        });
+        Ok(())
+    }
+
+    /// Closes the running round once the reveal period is over and starts the
+    /// next one. The leading share wins if the round met the quorum and the
+    /// share the win threshold.
+    pub fn finalize(&mut self) -> Result<U256, KnowledgeShareError> {
+        self.pausable.when_not_paused()?;
+        let (round_id, phase, _, ends_at) = self.current_round();
+        if phase != VOTING {
+            return Err(KnowledgeShareError::RoundNotInVoting(RoundNotInVoting { round_id }));
+        }
+        if block::timestamp() < ends_at {
+            return Err(KnowledgeShareError::VotingStillOpen(VotingStillOpen { round_id, ends_at }));
         }
+        let winner = self.leading_share(round_id);
+        self.finalize_round(round_id, winner);
+        Ok(round_id)
     }
 }
@@ -3,7 +3,14 @@
 extern crate alloc;
 
 use std::cmp::Ordering;
-use stylus_sdk::{alloy_primitives::U256, prelude::*};
+use stylus_common::access_control::{
+    AccessControl, AccessControlBadConfirmation, AccessControlUnauthorizedAccount,
+};
+use stylus_common::ownable::{
+    InvalidInitialization, Ownable, OwnableInvalidOwner, OwnableUnauthorizedAccount,
+};
+use stylus_common::pausable::{EnforcedPause, ExpectedPause, Pausable};
+use stylus_sdk::{alloy_primitives::{Address, U256}, prelude::*};
 
 #[derive(Default, Copy, Clone)]
 #[repr(transparent)]
@@ -880,12 +887,51 @@ fn forward_propagation(x: &[[SoftF64; 28]; 28]) -> U256 {
 sol_storage! {
     #[entrypoint]
     pub struct Counter {
+        #[borrow]
+        Ownable ownable;
+        #[borrow]
+        Pausable pausable;
+        #[borrow]
+        AccessControl access_control;
     }
 }
 
+#[derive(SolidityError)]
+pub enum CounterError {
+    EnforcedPause(EnforcedPause),
+    ExpectedPause(ExpectedPause),
+    AccessControlUnauthorizedAccount(AccessControlUnauthorizedAccount),
+    AccessControlBadConfirmation(AccessControlBadConfirmation),
+    InvalidInitialization(InvalidInitialization),
+    OwnableUnauthorizedAccount(OwnableUnauthorizedAccount),
+    OwnableInvalidOwner(OwnableInvalidOwner),
+}
+
+stylus_common::impl_component_errors!(CounterError);
+
 #[public]
+#[inherit(Ownable, Pausable, AccessControl)]
 impl Counter {
-    pub fn classify(&self, mat: Vec<Vec<U256>>) -> U256 {
+    pub fn initialize(&mut self, owner: Address) -> Result<(), CounterError> {
+        Ok(self.ownable.initialize(&mut self.access_control, owner)?)
+    }
+
+    pub fn accept_ownership(&mut self) -> Result<(), CounterError> {
+        Ok(self.ownable.accept_ownership(&mut self.access_control)?)
+    }
+
+    /// Halts classification.
+    pub fn pause(&mut self) -> Result<(), CounterError> {
+        Ok(self.pausable.pause(&self.access_control)?)
+    }
+
+    pub fn unpause(&mut self) -> Result<(), CounterError> {
+        Ok(self.pausable.unpause(&self.access_control)?)
+    }
+
+    pub fn classify(&self, mat: Vec<Vec<U256>>) -> Result<U256, CounterError> {
+        self.pausable.when_not_paused()?;
+
         let mut matrix: [[SoftF64; 28]; 28] = [[SoftF64(0.0_f64); 28]; 28];
         for i in 0..28 {
             for j in 0..28 {
@@ -896,6 +942,6 @@ impl Counter {
             }
         }
 
-        forward_propagation(&mut matrix)
+        Ok(forward_propagation(&mut matrix))
     }
 }
\ No newline at end of file
@@ -0,0 +1,139 @@
+//! Role-based access control in the style of OpenZeppelin's `AccessControl`.
+//!
+//! Roles are `bytes32` identifiers, usually the keccak256 of their name. Each
+//! role has an admin role whose members may grant and revoke it; by default
+//! that is `DEFAULT_ADMIN_ROLE`. Contracts embed the component with `#[borrow]`
+//! and `#[inherit(AccessControl)]` to expose the public methods below.
+
+use alloy_primitives::{b256, Address, B256};
+use alloy_sol_types::sol;
+use stylus_sdk::{evm, msg, prelude::*};
+
+pub const DEFAULT_ADMIN_ROLE: B256 = B256::ZERO;
+/// keccak256("PAUSER_ROLE")
+pub const PAUSER_ROLE: B256 =
+    b256!("65d7a28e3265b37a6474929f336521b332c1681b933f6cb9f3376673440d862a");
+
+sol_storage! {
+    pub struct AccessControl {
+        mapping(bytes32 => RoleData) roles;
+    }
+
+    pub struct RoleData {
+        mapping(address => bool) members;
+        bytes32 admin_role;
+    }
+}
+
+sol! {
+    event RoleAdminChanged(bytes32 indexed role, bytes32 indexed previous_admin_role, bytes32 indexed new_admin_role);
+    event RoleGranted(bytes32 indexed role, address indexed account, address indexed sender);
+    event RoleRevoked(bytes32 indexed role, address indexed account, address indexed sender);
+
+    error AccessControlUnauthorizedAccount(address account, bytes32 needed_role);
+    error AccessControlBadConfirmation();
+}
+
+#[derive(SolidityError)]
+pub enum AccessControlError {
+    AccessControlUnauthorizedAccount(AccessControlUnauthorizedAccount),
+    AccessControlBadConfirmation(AccessControlBadConfirmation),
+}
+
+#[public]
+impl AccessControl {
+    pub fn has_role(&self, role: B256, account: Address) -> bool {
+        self.roles.getter(role).members.get(account)
+    }
+
+    pub fn get_role_admin(&self, role: B256) -> B256 {
+        self.roles.getter(role).admin_role.get()
+    }
+
+    pub fn grant_role(&mut self, role: B256, account: Address) -> Result<(), AccessControlError> {
+        self.check_role(self.get_role_admin(role), msg::sender())?;
+        self.grant(role, account);
+        Ok(())
+    }
+
+    pub fn revoke_role(&mut self, role: B256, account: Address) -> Result<(), AccessControlError> {
+        self.check_role(self.get_role_admin(role), msg::sender())?;
+        self.revoke(role, account);
+        Ok(())
+    }
+
+    /// Drops `role` from the caller, who must pass their own address to
+    /// confirm.
+    pub fn renounce_role(
+        &mut self,
+        role: B256,
+        caller_confirmation: Address,
+    ) -> Result<(), AccessControlError> {
+        if caller_confirmation != msg::sender() {
+            return Err(AccessControlError::AccessControlBadConfirmation(
+                AccessControlBadConfirmation {},
+            ));
+        }
+        self.revoke(role, caller_confirmation);
+        Ok(())
+    }
+}
+
+impl AccessControl {
+    pub fn check_role(&self, role: B256, account: Address) -> Result<(), AccessControlError> {
+        if !self.has_role(role, account) {
+            return Err(AccessControlError::AccessControlUnauthorizedAccount(
+                AccessControlUnauthorizedAccount {
+                    account,
+                    needed_role: role,
+                },
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fails unless the caller holds `role`.
+    pub fn only_role(&self, role: B256) -> Result<(), AccessControlError> {
+        self.check_role(role, msg::sender())
+    }
+
+    pub fn set_role_admin(&mut self, role: B256, admin_role: B256) {
+        let previous_admin_role = self.get_role_admin(role);
+        self.roles.setter(role).admin_role.set(admin_role);
+        evm::log(RoleAdminChanged {
+            role,
+            previous_admin_role,
+            new_admin_role: admin_role,
+        });
+    }
+
+    /// Grants `role` without checking the caller; returns whether the account
+    /// did not have it yet.
+    pub fn grant(&mut self, role: B256, account: Address) -> bool {
+        if self.has_role(role, account) {
+            return false;
+        }
+        self.roles.setter(role).members.setter(account).set(true);
+        evm::log(RoleGranted {
+            role,
+            account,
+            sender: msg::sender(),
+        });
+        true
+    }
+
+    /// Revokes `role` without checking the caller; returns whether the
+    /// account had it.
+    pub fn revoke(&mut self, role: B256, account: Address) -> bool {
+        if !self.has_role(role, account) {
+            return false;
+        }
+        self.roles.setter(role).members.setter(account).set(false);
+        evm::log(RoleRevoked {
+            role,
+            account,
+            sender: msg::sender(),
+        });
+        true
+    }
+}
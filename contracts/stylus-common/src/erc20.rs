@@ -0,0 +1,11 @@
+//! The part of the ERC-20 interface the contracts call on tokens they accept
+//! as payment or stake.
+
+use stylus_sdk::prelude::*;
+
+sol_interface! {
+    interface IERC20 {
+        function transfer(address to, uint256 value) external returns (bool);
+        function transferFrom(address from, address to, uint256 value) external returns (bool);
+    }
+}
@@ -0,0 +1,59 @@
+//! Components shared by the Stylus contracts. Each one is a storage struct
+//! meant to be embedded as a field of a contract's `sol_storage!` struct,
+//! with the contract either inheriting its public methods or exposing its own
+//! on top of it.
+extern crate alloc;
+
+pub mod access_control;
+pub mod erc20;
+pub mod ownable;
+pub mod pausable;
+pub mod upgradeable;
+
+/// Implements `From` for the errors of `AccessControl`, `Ownable` and
+/// `Pausable`, so their methods can be called with `?` from a contract whose
+/// error enum has a variant of the same name for each of their errors.
+#[macro_export]
+macro_rules! impl_component_errors {
+    ($error:ty) => {
+        impl From<$crate::access_control::AccessControlError> for $error {
+            fn from(err: $crate::access_control::AccessControlError) -> Self {
+                use $crate::access_control::AccessControlError;
+                match err {
+                    AccessControlError::AccessControlUnauthorizedAccount(err) => {
+                        Self::AccessControlUnauthorizedAccount(err)
+                    }
+                    AccessControlError::AccessControlBadConfirmation(err) => {
+                        Self::AccessControlBadConfirmation(err)
+                    }
+                }
+            }
+        }
+
+        impl From<$crate::ownable::OwnableError> for $error {
+            fn from(err: $crate::ownable::OwnableError) -> Self {
+                use $crate::ownable::OwnableError;
+                match err {
+                    OwnableError::InvalidInitialization(err) => Self::InvalidInitialization(err),
+                    OwnableError::OwnableUnauthorizedAccount(err) => {
+                        Self::OwnableUnauthorizedAccount(err)
+                    }
+                    OwnableError::OwnableInvalidOwner(err) => Self::OwnableInvalidOwner(err),
+                }
+            }
+        }
+
+        impl From<$crate::pausable::PausableError> for $error {
+            fn from(err: $crate::pausable::PausableError) -> Self {
+                use $crate::pausable::PausableError;
+                match err {
+                    PausableError::EnforcedPause(err) => Self::EnforcedPause(err),
+                    PausableError::ExpectedPause(err) => Self::ExpectedPause(err),
+                    PausableError::AccessControlUnauthorizedAccount(err) => {
+                        Self::AccessControlUnauthorizedAccount(err)
+                    }
+                }
+            }
+        }
+    };
+}
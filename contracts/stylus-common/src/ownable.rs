@@ -0,0 +1,197 @@
+//! One-shot initialization and two-step ownership transfer.
+//!
+//! Stylus contracts have no constructor, so a freshly deployed contract is
+//! initialized by a separate call. To keep anyone else from front-running it,
+//! build with `STYLUS_DEPLOYER=<address>` set: `initialize` then only accepts
+//! that account. Without it, initialize right after deploying and check
+//! `owner()` before relying on the contract.
+//!
+//! The owner starts out with `DEFAULT_ADMIN_ROLE`, hands it on with ownership
+//! and alone may upgrade the contract (see `upgradeable`). Contracts embed
+//! `Ownable` with `#[borrow]` and `#[inherit(Ownable)]`, and expose
+//! `initialize` and `accept_ownership` themselves since those also touch
+//! their `AccessControl`.
+
+use alloy_primitives::{Address, B256};
+use alloy_sol_types::sol;
+use stylus_sdk::{abi::Bytes, evm, msg, prelude::*};
+
+use crate::access_control::{AccessControl, DEFAULT_ADMIN_ROLE};
+use crate::upgradeable::{self, UpgradeableError};
+
+sol_storage! {
+    pub struct Ownable {
+        bool initialized;
+        address owner;
+        address pending_owner;
+    }
+}
+
+sol! {
+    event Initialized(address indexed owner);
+    event OwnershipTransferStarted(address indexed previous_owner, address indexed new_owner);
+    event OwnershipTransferred(address indexed previous_owner, address indexed new_owner);
+
+    error InvalidInitialization();
+    error OwnableUnauthorizedAccount(address account);
+    error OwnableInvalidOwner(address owner);
+}
+
+#[derive(SolidityError)]
+pub enum OwnableError {
+    InvalidInitialization(InvalidInitialization),
+    OwnableUnauthorizedAccount(OwnableUnauthorizedAccount),
+    OwnableInvalidOwner(OwnableInvalidOwner),
+}
+
+/// The only account `initialize` accepts, if any. A malformed
+/// `STYLUS_DEPLOYER` fails the build instead of every `initialize` call.
+const DEPLOYER: Option<Address> = match option_env!("STYLUS_DEPLOYER") {
+    Some(deployer) => Some(parse_address(deployer)),
+    None => None,
+};
+
+const fn parse_address(hex: &str) -> Address {
+    let digits = match hex.as_bytes() {
+        [b'0', b'x' | b'X', digits @ ..] => digits,
+        digits => digits,
+    };
+    if digits.len() != 40 {
+        panic!("STYLUS_DEPLOYER must be a 20-byte hex address");
+    }
+    let mut bytes = [0u8; 20];
+    let mut i = 0;
+    while i < 20 {
+        bytes[i] = hex_digit(digits[2 * i]) << 4 | hex_digit(digits[2 * i + 1]);
+        i += 1;
+    }
+    Address::new(bytes)
+}
+
+const fn hex_digit(digit: u8) -> u8 {
+    match digit {
+        b'0'..=b'9' => digit - b'0',
+        b'a'..=b'f' => digit - b'a' + 10,
+        b'A'..=b'F' => digit - b'A' + 10,
+        _ => panic!("STYLUS_DEPLOYER must be a 20-byte hex address"),
+    }
+}
+
+#[public]
+impl Ownable {
+    pub fn owner(&self) -> Address {
+        self.owner.get()
+    }
+
+    pub fn pending_owner(&self) -> Address {
+        self.pending_owner.get()
+    }
+
+    /// Starts handing the contract over to `new_owner`, who completes it with
+    /// `accept_ownership`.
+    pub fn transfer_ownership(&mut self, new_owner: Address) -> Result<(), OwnableError> {
+        self.only_owner()?;
+        self.pending_owner.set(new_owner);
+        evm::log(OwnershipTransferStarted {
+            previous_owner: self.owner.get(),
+            new_owner,
+        });
+        Ok(())
+    }
+
+    /// Moves the proxy to `new_implementation`, delegate-calling it with
+    /// `data` when that is not empty. Owner only.
+    pub fn upgrade_to_and_call(
+        &mut self,
+        new_implementation: Address,
+        data: Bytes,
+    ) -> Result<(), UpgradeableError> {
+        if self.owner.get() != msg::sender() {
+            return Err(UpgradeableError::OwnableUnauthorizedAccount(
+                OwnableUnauthorizedAccount {
+                    account: msg::sender(),
+                },
+            ));
+        }
+        upgradeable::upgrade_to_and_call(new_implementation, &data)
+    }
+
+    pub fn implementation(&self) -> Address {
+        upgradeable::implementation()
+    }
+
+    #[selector(name = "proxiableUUID")]
+    pub fn proxiable_uuid(&self) -> Result<B256, UpgradeableError> {
+        upgradeable::proxiable_uuid()
+    }
+}
+
+impl Ownable {
+    pub fn is_initialized(&self) -> bool {
+        self.initialized.get()
+    }
+
+    /// Sets the first owner and makes it the admin of every role. Succeeds
+    /// only once, and only for the deployer when one was configured at build
+    /// time.
+    pub fn initialize(
+        &mut self,
+        access_control: &mut AccessControl,
+        owner: Address,
+    ) -> Result<(), OwnableError> {
+        if self.initialized.get() || DEPLOYER.is_some_and(|deployer| deployer != msg::sender()) {
+            return Err(OwnableError::InvalidInitialization(
+                InvalidInitialization {},
+            ));
+        }
+        if owner.is_zero() {
+            return Err(OwnableError::OwnableInvalidOwner(OwnableInvalidOwner {
+                owner,
+            }));
+        }
+        self.initialized.set(true);
+        self.owner.set(owner);
+        evm::log(Initialized { owner });
+        evm::log(OwnershipTransferred {
+            previous_owner: Address::ZERO,
+            new_owner: owner,
+        });
+        access_control.grant(DEFAULT_ADMIN_ROLE, owner);
+        Ok(())
+    }
+
+    pub fn only_owner(&self) -> Result<(), OwnableError> {
+        if self.owner.get() != msg::sender() {
+            return Err(OwnableError::OwnableUnauthorizedAccount(
+                OwnableUnauthorizedAccount {
+                    account: msg::sender(),
+                },
+            ));
+        }
+        Ok(())
+    }
+
+    /// Completes a transfer started by `transfer_ownership`, moving the admin
+    /// role from the previous owner to the caller.
+    pub fn accept_ownership(
+        &mut self,
+        access_control: &mut AccessControl,
+    ) -> Result<(), OwnableError> {
+        let new_owner = msg::sender();
+        if self.pending_owner.get() != new_owner {
+            return Err(OwnableError::OwnableUnauthorizedAccount(
+                OwnableUnauthorizedAccount { account: new_owner },
+            ));
+        }
+        let previous_owner = self.owner.get();
+        self.owner.set(new_owner);
+        self.pending_owner.set(Address::ZERO);
+        evm::log(OwnershipTransferred {
+            previous_owner,
+            new_owner,
+        });
+        access_control.revoke(DEFAULT_ADMIN_ROLE, previous_owner);
+        access_control.grant(DEFAULT_ADMIN_ROLE, new_owner);
+        Ok(())
+    }
+}
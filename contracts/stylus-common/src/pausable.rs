@@ -0,0 +1,90 @@
+//! Emergency stop for a contract's state-changing methods.
+//!
+//! Holders of `PAUSER_ROLE` and the admin may pause, while unpausing is left
+//! to the admin. Contracts embed `Pausable` with `#[borrow]` and
+//! `#[inherit(Pausable)]`, expose `pause` and `unpause` on top of their
+//! `AccessControl` and guard their state-changing methods with
+//! `when_not_paused`.
+
+use alloy_sol_types::sol;
+use stylus_sdk::{evm, msg, prelude::*};
+
+use crate::access_control::{
+    AccessControl, AccessControlUnauthorizedAccount, DEFAULT_ADMIN_ROLE, PAUSER_ROLE,
+};
+
+sol_storage! {
+    pub struct Pausable {
+        bool paused;
+    }
+}
+
+sol! {
+    event Paused(address account);
+    event Unpaused(address account);
+
+    error EnforcedPause();
+    error ExpectedPause();
+}
+
+#[derive(SolidityError)]
+pub enum PausableError {
+    EnforcedPause(EnforcedPause),
+    ExpectedPause(ExpectedPause),
+    AccessControlUnauthorizedAccount(AccessControlUnauthorizedAccount),
+}
+
+#[public]
+impl Pausable {
+    pub fn paused(&self) -> bool {
+        self.paused.get()
+    }
+}
+
+impl Pausable {
+    /// Fails with `EnforcedPause` while the contract is paused.
+    pub fn when_not_paused(&self) -> Result<(), PausableError> {
+        if self.paused.get() {
+            return Err(PausableError::EnforcedPause(EnforcedPause {}));
+        }
+        Ok(())
+    }
+
+    /// Pauses on behalf of the caller, who must be a pauser or the admin.
+    pub fn pause(&mut self, access_control: &AccessControl) -> Result<(), PausableError> {
+        let account = msg::sender();
+        if !access_control.has_role(PAUSER_ROLE, account)
+            && !access_control.has_role(DEFAULT_ADMIN_ROLE, account)
+        {
+            return Err(PausableError::AccessControlUnauthorizedAccount(
+                AccessControlUnauthorizedAccount {
+                    account,
+                    needed_role: PAUSER_ROLE,
+                },
+            ));
+        }
+        self.when_not_paused()?;
+        self.paused.set(true);
+        evm::log(Paused { account });
+        Ok(())
+    }
+
+    /// Unpauses on behalf of the caller, who must be the admin.
+    pub fn unpause(&mut self, access_control: &AccessControl) -> Result<(), PausableError> {
+        let account = msg::sender();
+        if !access_control.has_role(DEFAULT_ADMIN_ROLE, account) {
+            return Err(PausableError::AccessControlUnauthorizedAccount(
+                AccessControlUnauthorizedAccount {
+                    account,
+                    needed_role: DEFAULT_ADMIN_ROLE,
+                },
+            ));
+        }
+        if !self.paused.get() {
+            return Err(PausableError::ExpectedPause(ExpectedPause {}));
+        }
+        self.paused.set(false);
+        evm::log(Unpaused { account });
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+//! UUPS upgrades for contracts deployed behind an ERC-1967 proxy.
+//!
+//! The proxy (e.g. OpenZeppelin's `ERC1967Proxy`) is deployed with the Stylus
+//! contract as its implementation and `initialize(...)` as its constructor
+//! data, and keeps one address for good. The implementation stores its own
+//! address in the ERC-1967 slot, which `sol_storage!` never reaches, and only
+//! the contract's own checks decide who may upgrade.
+//!
+//! `Ownable` exposes these functions as owner-only public methods.
+//!
+//! Once a contract runs behind a proxy its storage layout is frozen: new
+//! fields go at the end of the entrypoint struct, never between existing ones
+//! or inside the embedded components.
+
+use alloy_primitives::{b256, Address, B256, U256};
+use alloy_sol_types::sol;
+use stylus_sdk::{
+    call::{self, Call},
+    evm,
+    prelude::*,
+    storage::StorageAddress,
+};
+
+use crate::ownable::OwnableUnauthorizedAccount;
+
+/// bytes32(uint256(keccak256("eip1967.proxy.implementation")) - 1)
+pub const IMPLEMENTATION_SLOT: B256 =
+    b256!("360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc");
+
+sol! {
+    event Upgraded(address indexed implementation);
+
+    error ERC1967InvalidImplementation(address implementation);
+    error UUPSUnauthorizedCallContext();
+    error UUPSUnsupportedProxiableUUID(bytes32 slot);
+    error FailedCall();
+}
+
+sol_interface! {
+    interface IERC1822Proxiable {
+        function proxiableUUID() external view returns (bytes32);
+    }
+}
+
+#[derive(SolidityError)]
+pub enum UpgradeableError {
+    ERC1967InvalidImplementation(ERC1967InvalidImplementation),
+    UUPSUnauthorizedCallContext(UUPSUnauthorizedCallContext),
+    UUPSUnsupportedProxiableUUID(UUPSUnsupportedProxiableUUID),
+    FailedCall(FailedCall),
+    OwnableUnauthorizedAccount(OwnableUnauthorizedAccount),
+}
+
+fn implementation_slot() -> StorageAddress {
+    // Safety: nothing else in the contract maps to the ERC-1967 slot.
+    unsafe { StorageAddress::new(U256::from_be_bytes(IMPLEMENTATION_SLOT.0), 0) }
+}
+
+/// Implementation the proxy currently delegates to, or zero when the code is
+/// called directly rather than through a proxy.
+pub fn implementation() -> Address {
+    implementation_slot().get()
+}
+
+/// ERC-1822 `proxiableUUID`. Reverts through a proxy, so a proxy can never be
+/// made the implementation of another one.
+pub fn proxiable_uuid() -> Result<B256, UpgradeableError> {
+    if !implementation().is_zero() {
+        return Err(UpgradeableError::UUPSUnauthorizedCallContext(
+            UUPSUnauthorizedCallContext {},
+        ));
+    }
+    Ok(IMPLEMENTATION_SLOT)
+}
+
+/// Points the proxy at `new_implementation` and, unless `data` is empty,
+/// delegate-calls it with `data` (e.g. to migrate state). Must be called
+/// through the proxy, after the caller passed the contract's own checks.
+pub fn upgrade_to_and_call(
+    new_implementation: Address,
+    data: &[u8],
+) -> Result<(), UpgradeableError> {
+    if implementation().is_zero() {
+        return Err(UpgradeableError::UUPSUnauthorizedCallContext(
+            UUPSUnauthorizedCallContext {},
+        ));
+    }
+    let slot = IERC1822Proxiable::new(new_implementation)
+        .proxiable_uuid(Call::new())
+        .map_err(|_| {
+            UpgradeableError::ERC1967InvalidImplementation(ERC1967InvalidImplementation {
+                implementation: new_implementation,
+            })
+        })?;
+    if slot != IMPLEMENTATION_SLOT {
+        return Err(UpgradeableError::UUPSUnsupportedProxiableUUID(
+            UUPSUnsupportedProxiableUUID { slot },
+        ));
+    }
+
+    implementation_slot().set(new_implementation);
+    evm::log(Upgraded {
+        implementation: new_implementation,
+    });
+
+    if !data.is_empty() {
+        // Safety: the new implementation was just vetted by the caller.
+        unsafe { call::delegate_call(Call::new(), new_implementation, data) }
+            .map_err(|_| UpgradeableError::FailedCall(FailedCall {}))?;
+    }
+    Ok(())
+}
@@ -1,12 +1,12 @@
 import express from 'express';
-import { JsonRpcProvider, Wallet, isAddress, Contract } from 'ethers';
+import { JsonRpcProvider, Wallet, isAddress, Contract, getAddress, hexlify, randomBytes, verifyMessage } from 'ethers';
 import OpenAI from 'openai';
 import dotenv from 'dotenv';
 import cors from "cors";
 
 dotenv.config();
 
-const { OPENAI_API_KEY, PRIVATE_KEY, RPC_URL, PORT } = process.env;
+const { OPENAI_API_KEY, PRIVATE_KEY, RPC_URL, PORT, IPFS_GATEWAY } = process.env;
 
 if (!OPENAI_API_KEY || !PRIVATE_KEY || !RPC_URL) {
   console.error('Error: Missing required environment variables.');
@@ -14,18 +14,38 @@ if (!OPENAI_API_KEY || !PRIVATE_KEY || !RPC_URL) {
 }
 
 const CONTRACT_ADDRESS = '0x104f5cc5d1593f1ba2a0eecf5882be85e231aca9';
+const GPT_SERVICE_ID = 2n;
 const ABI = [
-  "function purchase() external payable returns (uint256)",
-  "function balanceOf(address _address) external view returns (uint256)",
-  "function markUsage(address _address) external returns (uint256)"
+  "function purchase(uint256 service_id) external payable returns (uint256)",
+  "function balanceOf(address _address, uint256 service_id) external view returns (uint256)",
+  "function markUsage(address _address, uint256 service_id) external returns (uint256)",
+  "function remainingQuota(address user) external view returns (uint256, uint256)",
+  "function hasActiveSubscription(address user, uint256 service_id) external view returns (bool)"
 ];
 
-const generalKnowledgeSentences = [
-  "The Earth revolves around the Sun.",
-  "Water boils at 100 degrees Celsius.",
-  "Light travels faster than sound.",
-  "The human body has 206 bones."
+const KNOWLEDGE_ADDRESS = '0xa95799b35a9b71d8793ff8f160ac447b2ccb96f6';
+const KNOWLEDGE_ABI = [
+  "function knowledgeCount() external view returns (uint256)",
+  "function getKnowledge(uint256 offset, uint256 limit) external view returns (string[] memory)",
+  "event KnowledgeAccepted(uint256 indexed knowledge_index, uint256 indexed round_id, address indexed submitter, string knowledge)",
+  "event KnowledgeRemoved(uint256 indexed knowledge_index)"
 ];
+const KNOWLEDGE_PAGE_SIZE = 50n;
+const CONTENT_PREFIX = 'ipfs://';
+const ipfsGateway = IPFS_GATEWAY || 'https://ipfs.io/ipfs/';
+const IPFS_TIMEOUT_MS = 10000;
+const IPFS_MAX_BYTES = 16 * 1024;
+// Knowledge sent along with each query, in characters, leaving gpt-4 room for the query and answer
+const PROMPT_KNOWLEDGE_CHARS = 12000;
+
+// Accepted knowledge from the KnowledgeShare contract, by index
+const knowledgeSentences = [];
+
+// Single-use nonces a caller signs to prove they control the address charged
+const NONCE_TTL_MS = 5 * 60 * 1000;
+const nonces = new Map();
+
+const authMessage = (address, nonce) => `Query AI as ${address}\nNonce: ${nonce}`;
 
 // -----------------------------
 // Initialize Express App
@@ -50,26 +70,163 @@ const openai = new OpenAI({
 const provider = new JsonRpcProvider(RPC_URL);
 const wallet = new Wallet(PRIVATE_KEY, provider);
 const contract = new Contract(CONTRACT_ADDRESS, ABI, wallet);
+const knowledgeContract = new Contract(KNOWLEDGE_ADDRESS, KNOWLEDGE_ABI, provider);
+
+// -----------------------------
+// Load Accepted Knowledge
+// -----------------------------
+
+// Longer knowledge is stored on IPFS and referenced on-chain by its CID
+const resolveKnowledge = async (entry) => {
+  if (!entry.startsWith(CONTENT_PREFIX)) {
+    return entry;
+  }
+  const controller = new AbortController();
+  const timeout = setTimeout(() => controller.abort(), IPFS_TIMEOUT_MS);
+  try {
+    const response = await fetch(`${ipfsGateway}${entry.slice(CONTENT_PREFIX.length)}`, {
+      signal: controller.signal
+    });
+    if (!response.ok) {
+      throw new Error(`IPFS gateway returned ${response.status} for ${entry}`);
+    }
+    if (Number(response.headers.get('content-length')) > IPFS_MAX_BYTES) {
+      throw new Error(`${entry} is larger than ${IPFS_MAX_BYTES} bytes`);
+    }
+
+    // Content-Length may be missing, so count the bytes as they arrive
+    const chunks = [];
+    let size = 0;
+    for await (const chunk of response.body) {
+      size += chunk.length;
+      if (size > IPFS_MAX_BYTES) {
+        controller.abort();
+        throw new Error(`${entry} is larger than ${IPFS_MAX_BYTES} bytes`);
+      }
+      chunks.push(chunk);
+    }
+    return Buffer.concat(chunks).toString('utf8').trim();
+  } finally {
+    clearTimeout(timeout);
+  }
+};
+
+const loadKnowledge = async () => {
+  const count = await knowledgeContract.knowledgeCount();
+  for (let offset = 0n; offset < count; offset += KNOWLEDGE_PAGE_SIZE) {
+    const page = await knowledgeContract.getKnowledge(offset, KNOWLEDGE_PAGE_SIZE);
+    const results = await Promise.allSettled(page.map(resolveKnowledge));
+    results.forEach((result, idx) => {
+      const knowledgeIndex = Number(offset) + idx;
+      if (result.status === 'fulfilled') {
+        knowledgeSentences[knowledgeIndex] = result.value;
+      } else {
+        console.error(`Skipping knowledge #${knowledgeIndex}:`, result.reason);
+      }
+    });
+  }
+  console.log(`Loaded ${count} knowledge entries`);
+};
+
+knowledgeContract.on('KnowledgeAccepted', async (knowledgeIndex, roundId, submitter, knowledge) => {
+  try {
+    knowledgeSentences[Number(knowledgeIndex)] = await resolveKnowledge(knowledge);
+    console.log(`Accepted knowledge #${knowledgeIndex} from round ${roundId}`);
+  } catch (error) {
+    console.error(`Failed to load knowledge #${knowledgeIndex}:`, error);
+  }
+});
+
+knowledgeContract.on('KnowledgeRemoved', (knowledgeIndex) => {
+  knowledgeSentences[Number(knowledgeIndex)] = '';
+  console.log(`Removed knowledge #${knowledgeIndex} after a dispute`);
+});
+
+loadKnowledge().catch((error) => {
+  console.error('Failed to load knowledge:', error);
+});
+
+const words = (text) => new Set(text.toLowerCase().match(/[a-z0-9]{3,}/g) || []);
+
+// Accepted knowledge sharing the most words with the query, up to the prompt budget
+const selectKnowledge = (query) => {
+  const queryWords = words(query);
+  const ranked = knowledgeSentences
+    .filter(Boolean)
+    .map((sentence) => ({
+      sentence,
+      score: [...words(sentence)].filter((word) => queryWords.has(word)).length
+    }))
+    .sort((a, b) => b.score - a.score);
+
+  const selected = [];
+  let length = 0;
+  for (const { sentence } of ranked) {
+    if (length + sentence.length + 1 > PROMPT_KNOWLEDGE_CHARS) {
+      continue;
+    }
+    selected.push(sentence);
+    length += sentence.length + 1;
+  }
+  return selected.join(' ');
+};
+
+app.get('/nonce/:ethAddress', (req, res) => {
+  if (!isAddress(req.params.ethAddress)) {
+    return res.status(400).json({ error: 'Invalid Ethereum address.' });
+  }
+  const address = getAddress(req.params.ethAddress);
+  const nonce = hexlify(randomBytes(16));
+  nonces.set(address, { nonce, expiresAt: Date.now() + NONCE_TTL_MS });
+  res.json({ message: authMessage(address, nonce) });
+});
 
 app.post('/query-ai', async (req, res) => {
   try {
-    const { query, ethAddress } = req.body;
+    const { query, ethAddress, signature } = req.body;
 
-    if (!query || !ethAddress) {
-      return res.status(400).json({ error: 'Missing query or ethAddress in request body.' });
+    if (!query || !ethAddress || !signature) {
+      return res.status(400).json({ error: 'Missing query, ethAddress or signature in request body.' });
     }
 
     if (!isAddress(ethAddress)) {
       return res.status(400).json({ error: 'Invalid Ethereum address.' });
     }
 
-    const balance = await contract.balanceOf(ethAddress);
+    // The operator wallet charges whoever is named, so the caller must prove
+    // they own the address by signing the nonce handed out for it
+    const address = getAddress(ethAddress);
+    const issued = nonces.get(address);
+    nonces.delete(address);
+    if (!issued || issued.expiresAt < Date.now()) {
+      return res.status(401).json({ error: 'Missing or expired nonce.' });
+    }
+    let signer;
+    try {
+      signer = verifyMessage(authMessage(address, issued.nonce), signature);
+    } catch {
+      signer = null;
+    }
+    if (signer !== address) {
+      return res.status(401).json({ error: 'Invalid signature.' });
+    }
+
+    const [balance, subscribed] = await Promise.all([
+      contract.balanceOf(address, GPT_SERVICE_ID),
+      contract.hasActiveSubscription(address, GPT_SERVICE_ID)
+    ]);
 
-    if (balance < 1n) {
+    if (balance < 1n && !subscribed) {
       return res.status(403).json({ error: 'Insufficient credits.' });
     }
 
-    const prompt = `${generalKnowledgeSentences.join(' ')}\n\n${query}`;
+    const [windowQuota, hourQuota] = await contract.remainingQuota(address);
+
+    if (windowQuota < 1n || hourQuota < 1n) {
+      return res.status(429).json({ error: 'Rate limit exceeded.' });
+    }
+
+    const prompt = `${selectKnowledge(query)}\n\n${query}`;
 
     const completion = await openai.chat.completions.create({
       model: "gpt-4",
@@ -85,11 +242,11 @@ app.post('/query-ai', async (req, res) => {
 
     // Optional: Mark Usage (State-Changing Operation)
     try {
-      const markUsageTx = await contract.markUsage(ethAddress);
+      const markUsageTx = await contract.markUsage(address, GPT_SERVICE_ID);
       await markUsageTx.wait(); // Wait for the transaction to be mined
-      console.log(`Marked usage for address: ${ethAddress}`);
+      console.log(`Marked usage for address: ${address}`);
     } catch (txError) {
-      console.error(`Failed to mark usage for address ${ethAddress}:`, txError);
+      console.error(`Failed to mark usage for address ${address}:`, txError);
       // Depending on requirements, you might want to handle this differently
     }
 
@@ -1,7 +1,7 @@
 import { useState, useEffect } from "react";
 import EthContext from "../context/EthContext";
 import { ethers, formatUnits } from "ethers";
-import { ABI_TALENT, AUTHORIZATION } from "../utils/contracts";
+import { ABI_TALENT, AUTHORIZATION, MNIST_SERVICE_ID } from "../utils/contracts";
 
 export default function Layout({ children }: { children: React.ReactNode }) {
   const [isAuthenticated, setIsAuthenticated] = useState<boolean>(false);
@@ -25,7 +25,7 @@ export default function Layout({ children }: { children: React.ReactNode }) {
           const contractAddress = AUTHORIZATION;
           const contract = new ethers.Contract(contractAddress, abi, provider);
 
-          const userBalance = await contract.balanceOf(userAddress);
+          const userBalance = await contract.balanceOf(userAddress, MNIST_SERVICE_ID);
           setBalance(userBalance);
         }
       }
@@ -50,13 +50,13 @@ export default function Layout({ children }: { children: React.ReactNode }) {
 
       // Create contract instance and fetch balance
       const abi = [
-        "function purchase() external payable returns (uint256)",
-        "function balanceOf(address _address) external view returns (uint256)",
+        "function purchase(uint256 service_id) external payable returns (uint256)",
+        "function balanceOf(address _address, uint256 service_id) external view returns (uint256)",
       ];
       const contractAddress = "0x104f5cc5d1593f1ba2a0eecf5882be85e231aca9";
       const contract = new ethers.Contract(contractAddress, abi, provider);
 
-      const userBalance = await contract.balanceOf(userAddress);
+      const userBalance = await contract.balanceOf(userAddress, MNIST_SERVICE_ID);
       setBalance(userBalance);
     } catch (error) {
       console.error("Wallet connection failed:", error);
@@ -1,7 +1,10 @@
 // src/components/ChatPage.tsx
 import React, { useState, useContext, useEffect, useRef } from 'react';
+import { BrowserProvider } from 'ethers';
 import EthContext from '../context/EthContext';
 
+const GPT_API = 'http://localhost:8000';
+
 interface Message {
   sender: 'user' | 'ai';
   content: string;
@@ -44,7 +47,16 @@ const ChatPage: React.FC = () => {
     setChatHistory((prev) => [...prev, { sender: 'user', content: inputMessage }]);
 
     try {
-      const response = await fetch('http://localhost:8000/query-ai', {
+      // Prove control of the address the backend charges by signing a fresh nonce
+      const nonceResponse = await fetch(`${GPT_API}/nonce/${address}`);
+      if (!nonceResponse.ok) {
+        throw new Error('Failed to fetch a signing nonce.');
+      }
+      const { message } = await nonceResponse.json();
+      const signer = await new BrowserProvider(window.ethereum).getSigner();
+      const signature = await signer.signMessage(message);
+
+      const response = await fetch(`${GPT_API}/query-ai`, {
         method: 'POST',
         headers: {
           'Content-Type': 'application/json'
@@ -52,6 +64,7 @@ const ChatPage: React.FC = () => {
         body: JSON.stringify({
           query: inputMessage,
           ethAddress: address,
+          signature,
         }),
       });
 
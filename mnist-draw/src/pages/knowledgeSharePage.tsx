@@ -1,14 +1,35 @@
 // src/components/KnowledgeSharePage.tsx
 import React, { useState, useEffect, useContext } from 'react';
-import { BrowserProvider, Contract, BigNumber } from 'ethers';
+import { BrowserProvider, Contract, BigNumber, ZeroAddress, hexlify, randomBytes, solidityPackedKeccak256 } from 'ethers';
 import EthContext from '../context/EthContext';
 import { KNOWLEDGE_ABI, KNOWLEDGE_PUBLISH } from '../utils/contracts';
 
+type SavedVote = {
+  index: number;
+  salt: string;
+};
+
+// Votes are committed first and revealed later, so the salt has to be kept around
+const voteKey = (account: string, roundId: bigint) => `knowledge-vote:${account}:${roundId}`;
+
 type KnowledgeItem = {
   address: string;
   knowledge: string;
 };
 
+const SHARES_PAGE_SIZE = 20n;
+
+// Fetch the shares of the running round page by page
+const fetchSubmittedKnowledge = async (contract: Contract): Promise<KnowledgeItem[]> => {
+  const [roundId] = await contract.currentRound();
+  const items: KnowledgeItem[] = [];
+  for (let offset = 0n; ; offset += SHARES_PAGE_SIZE) {
+    const [addresses, knowledgeList]: [string[], string[]] = await contract.roundShares(roundId, offset, SHARES_PAGE_SIZE);
+    addresses.forEach((addr, idx) => items.push({ address: addr, knowledge: knowledgeList[idx] }));
+    if (BigInt(addresses.length) < SHARES_PAGE_SIZE) return items;
+  }
+};
+
 const KnowledgeSharePage: React.FC = () => {
   const { isAuthenticated, address, balance, setBalance } = useContext(EthContext);
   const [contract, setContract] = useState<Contract | null>(null);
@@ -35,14 +56,7 @@ const KnowledgeSharePage: React.FC = () => {
           setIsRewardInProgress(rewardStatus);
 
           // Fetch submitted knowledge
-          const result: any = await contractInstance.getSubmittedKnowledge();
-          const addresses: string[] = result[0];
-          const knowledgeList: string[] = result[1];
-          const combinedList = addresses.map((addr, idx) => ({
-            address: addr,
-            knowledge: knowledgeList[idx],
-          }));
-          setSubmittedKnowledge(combinedList);
+          setSubmittedKnowledge(await fetchSubmittedKnowledge(contractInstance));
 
           // Fetch user's vote count
           const userVoteCount: BigNumber = await contractInstance.getVote();
@@ -76,45 +90,81 @@ const KnowledgeSharePage: React.FC = () => {
 
     try {
       setStatusMessage('Submitting knowledge to the blockchain...');
-      const tx = await contract.share(knowledge);
+      const [, , submissionFee] = await contract.rewardParameters();
+      const tx = await contract.share(knowledge, { value: submissionFee });
       await tx.wait();
       setStatusMessage('Knowledge shared successfully!');
       setKnowledge('');
 
       // Update submitted knowledge
-      const result: any = await contract.getSubmittedKnowledge();
-      const addresses: string[] = result[0];
-      const knowledgeList: string[] = result[1];
-      const combinedList = addresses.map((addr, idx) => ({
-        address: addr,
-        knowledge: knowledgeList[idx],
-      }));
-      setSubmittedKnowledge(combinedList);
+      setSubmittedKnowledge(await fetchSubmittedKnowledge(contract));
     } catch (error) {
       console.error('Error sharing knowledge:', error);
       setStatusMessage('Failed to share knowledge. Please try again.');
     }
   };
 
-  // Handle voting
+  // Handle voting: commit to the chosen share without revealing it yet
   const handleVote = async (index: number) => {
-    if (!contract) {
+    if (!contract || !address) {
       setStatusMessage('Smart contract is not initialized.');
       return;
     }
 
     try {
+      // Votes are staked; this page stakes the minimum in ETH
+      const [stakeToken, minStake] = await contract.stakingParameters();
+      if (stakeToken !== ZeroAddress) {
+        setStatusMessage('Voting requires staking tokens, which this page does not support yet.');
+        return;
+      }
+      const stake = minStake > 0n ? minStake : 1n;
+
+      const [roundId] = await contract.currentRound();
+      const salt = hexlify(randomBytes(32));
+      const commitment = solidityPackedKeccak256(
+        ['uint256', 'bytes32', 'address', 'uint256', 'address'],
+        [index, salt, address, roundId, KNOWLEDGE_PUBLISH]
+      );
+      localStorage.setItem(voteKey(address, roundId), JSON.stringify({ index, salt } as SavedVote));
+
       setStatusMessage('Submitting your vote...');
-      const tx = await contract.vote(index);
+      const tx = await contract.commitVote(commitment, stake, { value: stake });
       await tx.wait();
-      setStatusMessage('Vote submitted successfully!');
+      setStatusMessage('Vote committed! Come back to reveal it once committing closes.');
+    } catch (error) {
+      console.error('Error submitting vote:', error);
+      setStatusMessage('Failed to submit vote. Please try again.');
+    }
+  };
+
+  // Handle revealing the vote committed earlier in this round
+  const handleReveal = async () => {
+    if (!contract || !address) {
+      setStatusMessage('Smart contract is not initialized.');
+      return;
+    }
+
+    try {
+      const [roundId] = await contract.currentRound();
+      const saved = localStorage.getItem(voteKey(address, roundId));
+      if (!saved) {
+        setStatusMessage('No committed vote found for this round.');
+        return;
+      }
+      const { index, salt }: SavedVote = JSON.parse(saved);
+
+      setStatusMessage('Revealing your vote...');
+      const tx = await contract.revealVote(index, salt);
+      await tx.wait();
+      setStatusMessage('Vote revealed successfully!');
 
       // Update vote count
       const updatedVoteCount: BigNumber = await contract.getVote();
       setVoteCount(updatedVoteCount.toNumber());
     } catch (error) {
-      console.error('Error submitting vote:', error);
-      setStatusMessage('Failed to submit vote. Please try again.');
+      console.error('Error revealing vote:', error);
+      setStatusMessage('Failed to reveal vote. Please try again.');
     }
   };
 
@@ -181,6 +231,14 @@ const KnowledgeSharePage: React.FC = () => {
                 </button>
               </form>
             )}
+            {isRewardInProgress && (
+              <button
+                onClick={handleReveal}
+                className="w-full mb-6 bg-purple-600 text-white py-3 rounded-md hover:bg-purple-700 transition-colors font-semibold text-lg"
+              >
+                Reveal My Vote
+              </button>
+            )}
             {statusMessage && (
               <div className="mt-6 p-4 bg-green-100 text-green-800 rounded-md text-center">
                 {statusMessage}
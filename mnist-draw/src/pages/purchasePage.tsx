@@ -1,6 +1,6 @@
 import { useState, useEffect } from "react";
 import { ethers, parseUnits, formatEther } from "ethers";
-import { ABI_TALENT, AUTHORIZATION } from "../utils/contracts";
+import { ABI_TALENT, AUTHORIZATION, MNIST_SERVICE_ID } from "../utils/contracts";
 
 export default function PurchasePage() {
   const [isAuthenticated, setIsAuthenticated] = useState<boolean>(false);
@@ -32,7 +32,7 @@ export default function PurchasePage() {
           setContract(contractInstance);
 
           // Fetch user's balance
-          const userBalance = await contractInstance.balanceOf(userAddress);
+          const userBalance = await contractInstance.balanceOf(userAddress, MNIST_SERVICE_ID);
           setBalance(userBalance);
         } else {
           setIsAuthenticated(false);
@@ -52,11 +52,11 @@ export default function PurchasePage() {
     if (!contract) return;
     setPurchasing(true);
     try {
-      const tx = await contract.purchase({ value: ACCESS_COST });
+      const tx = await contract.purchase(MNIST_SERVICE_ID, { value: ACCESS_COST });
       await tx.wait();
 
       // Update balance after purchase
-      const userBalance = await contract.balanceOf(address);
+      const userBalance = await contract.balanceOf(address, MNIST_SERVICE_ID);
       setBalance(userBalance);
       alert("Purchase successful!");
       window.location.reload();
@@ -84,7 +84,7 @@ export default function PurchasePage() {
       const contractInstance = new ethers.Contract(CONTRACT_ADDRESS, ABI, signer);
       setContract(contractInstance);
 
-      const userBalance = await contractInstance.balanceOf(userAddress);
+      const userBalance = await contractInstance.balanceOf(userAddress, MNIST_SERVICE_ID);
       setBalance(userBalance);
     } catch (error) {
       console.error("Wallet connection failed:", error);
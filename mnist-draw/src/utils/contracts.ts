@@ -3,18 +3,30 @@
 // hai sa dam o tura sa-ti creasca adrenalina
 export const API_URL = "http://127.0.0.1:5000";
 export const AUTHORIZATION="0x104f5cc5d1593f1ba2a0eecf5882be85e231aca9";
+export const MNIST_SERVICE_ID = 1n;
 export const ABI_TALENT=[
-  "function purchase() external payable returns (uint256)",
-  "function balanceOf(address _address) external view returns (uint256)",
-  "function markUsage(address _address) external returns (uint256)"
+  "function purchase(uint256 service_id) external payable returns (uint256)",
+  "function balanceOf(address _address, uint256 service_id) external view returns (uint256)",
+  "function markUsage(address _address, uint256 service_id) external returns (uint256)"
 ];
 
 export const KNOWLEDGE_PUBLISH="0xa95799b35a9b71d8793ff8f160ac447b2ccb96f6";
 export const KNOWLEDGE_ABI = [
-  "function setOwner() external returns (bool)",
+  "function initialize(address owner) external",
   "function isRewardInProgress() external view returns (bool)",
-  "function share(string calldata knowledge) external",
+  "function share(string calldata knowledge) external payable",
+  "function shareContent(string calldata cid, string calldata title, string calldata category) external payable",
   "function getSubmittedKnowledge() external view returns (address[] memory, string[] memory)",
+  "function roundShares(uint256 round_id, uint256 offset, uint256 limit) external view returns (address[] memory, string[] memory, uint256[] memory)",
+  "function contributorStats(address contributor) external view returns (uint256, uint256)",
   "function getVote() external view returns (uint256)",
-  "function vote(uint256 index) external"
+  "function commitVote(bytes32 commitment, uint256 stake) external payable",
+  "function revealVote(uint256 index, bytes32 salt) external",
+  "function stakingParameters() external view returns (address, uint256, bool, uint256)",
+  "function withdrawStake(uint256 round_id) external returns (uint256)",
+  "function currentRound() external view returns (uint256, uint8, uint64, uint64)",
+  "function finalize() external returns (uint256)",
+  "function rewardParameters() external view returns (uint256, uint256, uint256)",
+  "function pendingRewards(address contributor) external view returns (uint256)",
+  "function claimRewards() external returns (uint256)"
 ];
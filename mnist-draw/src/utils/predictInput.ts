@@ -1,5 +1,5 @@
 import { BrowserProvider, Contract } from "ethers";
-import { ABI_TALENT, API_URL, AUTHORIZATION } from "./contracts";
+import { ABI_TALENT, API_URL, AUTHORIZATION, MNIST_SERVICE_ID } from "./contracts";
 
 // Define API URL and ABI
 
@@ -17,7 +17,7 @@ export default async function predictInput(input: number[][], address: string):
   const contract = new Contract(AUTHORIZATION, ABI_TALENT, signer);
 
   // Execute markUsage before fetching
-  await contract.markUsage(address);
+  await contract.markUsage(address, MNIST_SERVICE_ID);
 
   // Fetch prediction from API
   const response = await fetch(`${API_URL}/predict`, {